@@ -0,0 +1,157 @@
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::f2l::recognise_f2l;
+use crate::last_layer::{recognise_oll, recognise_pll};
+use crate::notation::{Layer::U, Move, invert, parse_alg, simplify};
+use crate::subsets;
+
+// The library lives in algs.txt, a case a line:
+//
+// set | case | algorithm | algorithm ...
+//
+// Cases are named the way the recognisers name them, the tests check
+// every algorithm against them.
+const LIBRARY: &str = include_str!("algs.txt");
+
+pub const SETS: [&str; 6] = ["OLL", "PLL", "F2L", "COLL", "CMLL", "ZBLL"];
+
+#[derive(Debug, Clone)]
+pub struct AlgCase {
+    pub set: &'static str,
+    pub case: &'static str,
+    // the state, that the first algorithm solves, the others take a turn
+    // of U before them to solve it as well
+    pub state: Cube,
+    pub algs: Vec<Vec<Move>>
+}
+
+fn undo(alg: &[Move]) -> Cube {
+    let mut state = Cube::default();
+    state.apply_alg(&invert(alg));
+    state
+}
+
+pub fn library() -> &'static [AlgCase] {
+    static CASES: OnceLock<Vec<AlgCase>> = OnceLock::new();
+    CASES.get_or_init(|| {
+        LIBRARY.lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut fields = line.split('|').map(str::trim);
+                let set = fields.next().unwrap();
+                let set = SETS.into_iter().find(|&s| s == set).unwrap();
+                let case = fields.next().unwrap();
+                let mut algs: Vec<Vec<Move>> = fields.map(|alg| parse_alg(alg).unwrap()).collect();
+                let state = undo(&algs[0]);
+                let auf = |state: &Cube| recognise(set, state).map_or(0, |(_, auf)| auf.amount);
+                let first = auf(&state);
+                for alg in algs.iter_mut().skip(1) {
+                    let turn = Move::new(U, first + 4 - auf(&undo(alg)));
+                    *alg = simplify(&[&[turn][..], alg].concat());
+                }
+                AlgCase { set, case, state, algs }
+            })
+            .collect()
+    })
+}
+
+// The case of the cube in the set, and the AUF that shows it the way the
+// algorithms take it
+pub fn recognise(set: &str, cube: &Cube) -> Option<(String, Move)> {
+    match set {
+        "OLL" => recognise_oll(cube).filter(|c| c.number > 0).map(|c| (c.number.to_string(), c.auf)),
+        "PLL" => recognise_pll(cube).filter(|c| c.name != "skip").map(|c| (c.name.to_string(), c.auf)),
+        "F2L" => recognise_f2l(cube).filter(|c| c.name != "skip").map(|c| (c.name, c.auf)),
+        "COLL" => subsets::coll().recognise(cube).filter(|c| c.name != "skip").map(|c| (c.name, c.auf)),
+        "CMLL" => subsets::cmll().recognise(cube).filter(|c| c.name != "skip").map(|c| (c.name, c.auf)),
+        "ZBLL" => subsets::zbll().recognise(cube).map(|c| (c.name, c.auf)),
+        _ => None
+    }
+}
+
+pub fn lookup(set: &str, case: &str) -> Option<&'static AlgCase> {
+    library().iter().find(|c| c.set == set && c.case == case)
+}
+
+// The case of the cube with the AUF, that turns it into the state of the
+// case. The algorithms solve it from there, up to a turn of U after them.
+pub fn algs_for(set: &str, cube: &Cube) -> Option<(Move, &'static AlgCase)> {
+    let (case, auf) = recognise(set, cube)?;
+    let found = lookup(set, &case)?;
+    let (_, state_auf) = recognise(set, &found.state)?;
+    Some((Move::new(U, auf.amount + 4 - state_auf.amount), found))
+}
+
+// Every set and case, that the algorithm solves
+pub fn solved_by(alg: &str) -> Vec<(&'static str, String)> {
+    let Some(alg) = parse_alg(alg) else {
+        return vec![];
+    };
+    let mut state = Cube::default();
+    state.apply_alg(&invert(&alg));
+    SETS.iter()
+        .filter_map(|&set| recognise(set, &state).map(|(case, _)| (set, case)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::last_layer::is_f2l_solved;
+    use crate::notation::alg_to_string;
+
+    fn is_solved_in(set: &str, cube: &Cube) -> bool {
+        match set {
+            "OLL" => recognise_oll(cube).is_some_and(|c| c.number == 0),
+            "PLL" => recognise_pll(cube).is_some_and(|c| c.name == "skip"),
+            "F2L" => recognise_f2l(cube).is_some_and(|c| c.name == "skip"),
+            "COLL" => subsets::coll().recognise(cube).is_some_and(|c| c.name == "skip"),
+            "CMLL" => subsets::cmll().recognise(cube).is_some_and(|c| c.name == "skip"),
+            _ => (0..4).any(|turn| {
+                let mut turned = cube.clone();
+                turned.apply(Move::new(U, turn));
+                turned == Cube::default()
+            })
+        }
+    }
+
+    #[test]
+    fn check_library() {
+        for set in SETS {
+            assert!(library().iter().any(|c| c.set == set), "{}", set);
+        }
+        for case in library() {
+            for alg in &case.algs {
+                let found = recognise(case.set, &undo(alg)).map(|(name, _)| name);
+                assert_eq!(found.as_deref(), Some(case.case), "{} {}", case.set, alg_to_string(alg));
+                let mut solved = case.state.clone();
+                solved.apply_alg(alg);
+                assert!(is_solved_in(case.set, &solved), "{} {}", case.set, alg_to_string(alg));
+            }
+        }
+        let count = |set| library().iter().filter(|c| c.set == set).count();
+        assert_eq!(SETS.map(count), [57, 21, 41, 42, 42, 80]);
+        for case in library() {
+            assert!(case.algs.len() >= 2, "{} {}", case.set, case.case);
+        }
+    }
+
+    #[test]
+    fn check_lookup() {
+        assert!(solved_by("R U R' U R U2 R'").contains(&("OLL", "27".to_string())));
+        assert_eq!(solved_by("M2 U M U2 M' U M2"), [("PLL", "Ua".to_string())]);
+        assert_eq!(solved_by("U R U' R'"), [("F2L", "F2L 1".to_string())]);
+        assert!(solved_by("R U R' U'").iter().all(|(set, _)| *set == "F2L"));
+        assert!(solved_by("nonsense").is_empty());
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg("U R U2 R' U' R U' R' U2").unwrap());
+        let (auf, case) = algs_for("OLL", &cube).unwrap();
+        assert_eq!(case.case, "27");
+        for alg in &case.algs {
+            let mut solved = cube.clone();
+            solved.apply(auf);
+            solved.apply_alg(alg);
+            assert!(is_f2l_solved(&solved) && recognise_oll(&solved).unwrap().number == 0);
+        }
+    }
+}
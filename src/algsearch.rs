@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+use std::fmt;
+use crate::{Cube, Stiker};
+use crate::facelet::*;
+use crate::notation::{Layer, Metric, Move, alg_to_string, invert, simplify};
+use crate::search::{Pruning, Search};
+
+// Cases are cubes, where the stickers that do not matter are V. An
+// algorithm solves the case, when every other sticker ends up on a facelet
+// of its colour.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundAlg {
+    pub pre_auf: Option<Move>,
+    pub moves: Vec<Move>,
+    pub post_auf: Option<Move>
+}
+
+impl FoundAlg {
+    pub fn full(&self) -> Vec<Move> {
+        self.pre_auf.iter().chain(&self.moves).chain(&self.post_auf).copied().collect()
+    }
+    // AUFs are not counted
+    pub fn count(&self, metric: Metric) -> usize {
+        metric.count(&self.moves)
+    }
+}
+
+impl fmt::Display for FoundAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(auf) = self.pre_auf {
+            write!(f, "({}) ", auf)?;
+        }
+        write!(f, "{}", alg_to_string(&self.moves))?;
+        if let Some(auf) = self.post_auf {
+            write!(f, " ({})", auf)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn sort_by_metric(algs: &mut [FoundAlg], metric: Metric) {
+    algs.sort_by_key(|alg| (alg.count(metric), alg.moves.len()));
+}
+
+// Move sets are written like <R,U,D>
+pub fn parse_move_set(set: &str) -> Option<Vec<Layer>> {
+    let inner = set.trim().strip_prefix('<')?.strip_suffix('>')?;
+    inner.split(',')
+        .map(|note| note.trim().parse::<Move>().ok().filter(|m| m.amount == 1).map(|m| m.layer))
+        .collect()
+}
+
+// The case, that the algorithm solves
+pub fn case_of(alg: &[Move]) -> Cube {
+    let mut cube = Cube::default();
+    cube.apply_alg(&invert(alg));
+    cube
+}
+
+pub fn solves(case: &Cube, alg: &[Move]) -> bool {
+    let solved = Cube::default();
+    let mut stickers: Vec<u8> = (0..STICKERS as u8).collect();
+    for &m in alg {
+        apply_to_stickers(&mut stickers, m);
+    }
+    stickers.iter().enumerate().all(|(from, &to)| {
+        let color = case.color(facelet(from as u8));
+        color == Stiker::V || solved.color(facelet(to)) == color
+    })
+}
+
+impl Cube {
+    pub fn masked(&self, keep: impl Fn(Facelet, Stiker) -> bool) -> Cube {
+        let mut cube = self.clone();
+        for (row, layer) in cube.scan.iter_mut().enumerate() {
+            for (col, letter) in layer.iter_mut().enumerate() {
+                if !keep((row, col), *letter) {
+                    *letter = Stiker::V;
+                }
+            }
+        }
+        cube
+    }
+}
+
+// Every piece with a known sticker is tracked by the sticker on the first
+// facelet of its slot, with the set of facelets it may end on
+fn track(case: &Cube) -> (Vec<u8>, Vec<u128>) {
+    let solved = Cube::default();
+    let mut slots: Vec<Vec<Facelet>> = CORNER_FACELETS.iter().map(|c| c.to_vec()).collect();
+    slots.extend(EDGE_FACELETS.iter().map(|e| e.to_vec()));
+    slots.extend(CENTER_FACELETS.iter().map(|&c| vec![c]));
+    let mut stickers = Vec::new();
+    let mut targets = Vec::new();
+    for slot in &slots {
+        let colors: Vec<Stiker> = slot.iter().map(|&f| case.color(f)).collect();
+        if colors.iter().all(|&c| c == Stiker::V) {
+            continue;
+        }
+        let size = slot.len();
+        let mut target = 0u128;
+        for other in slots.iter().filter(|s| s.len() == size) {
+            for twist in 0..size {
+                let fits = (0..size).all(|k| {
+                    colors[k] == Stiker::V || solved.color(other[(k + twist) % size]) == colors[k]
+                });
+                if fits {
+                    target |= 1 << sticker(other[twist]);
+                }
+            }
+        }
+        stickers.push(sticker(slot[0]));
+        targets.push(target);
+    }
+    (stickers, targets)
+}
+
+// Targets of the pieces before the given turn of U
+fn before(targets: &[u128], auf: Move) -> Vec<u128> {
+    let perm = move_perm(auf);
+    targets.iter().map(|&target| {
+        (0..STICKERS).filter(|&s| target >> perm[s] & 1 == 1)
+            .fold(0, |set, s| set | 1 << s)
+    }).collect()
+}
+
+fn combinations(sets: &[u128]) -> Vec<Vec<u8>> {
+    let mut result: Vec<Vec<u8>> = vec![vec![]];
+    for &set in sets {
+        let mut longer = Vec::new();
+        for partial in &result {
+            for s in (0..STICKERS as u8).filter(|&s| set >> s & 1 == 1 && !partial.contains(&s)) {
+                let mut next = partial.clone();
+                next.push(s);
+                longer.push(next);
+            }
+        }
+        result = longer;
+    }
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct AlgSearch {
+    pub layers: Vec<Layer>,
+    pub max_length: usize,
+    pub auf: bool
+}
+
+impl AlgSearch {
+    pub fn new(move_set: &str, max_length: usize) -> Option<Self> {
+        Some(AlgSearch { layers: parse_move_set(move_set)?, max_length, auf: true })
+    }
+    fn aufs(&self) -> Vec<Move> {
+        let turns = if self.auf { 4 } else { 1 };
+        (0..turns).map(|amount| Move::new(Layer::U, amount)).collect()
+    }
+    // Every algorithm up to max_length, shortest first. With AUF on, the
+    // algorithms neither start nor end with a U turn.
+    pub fn run(&self, case: &Cube) -> Vec<FoundAlg> {
+        let moves = Move::all(&self.layers);
+        let (stickers, targets) = track(case);
+        let goals: Vec<Vec<u128>> = self.aufs().iter().map(|&auf| before(&targets, auf)).collect();
+        let tables: Vec<Pruning> = (0..stickers.len()).step_by(3).map(|first| {
+            let pieces: Vec<usize> = (first..stickers.len().min(first + 3)).collect();
+            let mut starts = Vec::new();
+            for goal in &goals {
+                let sets: Vec<u128> = pieces.iter().map(|&i| goal[i]).collect();
+                for combination in combinations(&sets) {
+                    let mut start = vec![0; stickers.len()];
+                    for (&i, s) in pieces.iter().zip(combination) {
+                        start[i] = s;
+                    }
+                    starts.push(start);
+                }
+            }
+            Pruning::any_of(pieces, &starts, &moves, self.max_length as u8)
+        }).collect();
+        let post_auf = |s: &[u8]| goals.iter().position(|goal| {
+            s.iter().zip(goal).all(|(&s, &set)| set >> s & 1 == 1)
+        });
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for pre in self.aufs() {
+            let mut start = stickers.clone();
+            apply_to_stickers(&mut start, pre);
+            let search = Search { moves: &moves, tables: &tables, goal: &|s| post_auf(s).is_some() };
+            search.run(&start, self.max_length, &mut |path| {
+                let is_auf = |m: Option<&Move>| m.is_some_and(|m| m.layer == Layer::U);
+                if self.auf && (is_auf(path.first()) || is_auf(path.last())) {
+                    return false;
+                }
+                let mut end = start.clone();
+                for &m in path {
+                    apply_to_stickers(&mut end, m);
+                }
+                let post = self.aufs()[post_auf(&end).unwrap_or(0)];
+                let found = FoundAlg {
+                    pre_auf: Some(pre).filter(|m| m.amount != 0),
+                    moves: path.to_vec(),
+                    post_auf: Some(post).filter(|m| m.amount != 0)
+                };
+                if seen.insert(alg_to_string(&simplify(&found.full()))) {
+                    result.push(found);
+                }
+                false
+            });
+        }
+        result.sort_by_key(|alg| alg.moves.len());
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    const SUNE: &str = "R U R' U R U2 R'";
+
+    #[test]
+    fn check_parse_move_set() {
+        assert_eq!(parse_move_set("<R, U,D>"), Some(vec![Layer::R, Layer::U, Layer::D]));
+        assert_eq!(parse_move_set("<R,U2>"), None);
+        assert_eq!(parse_move_set("R,U"), None);
+    }
+
+    #[test]
+    fn check_sune() {
+        let case = case_of(&parse_alg(SUNE).unwrap());
+        let algs = AlgSearch::new("<R,U>", 7).unwrap().run(&case);
+        assert!(algs.iter().any(|a| a.to_string() == SUNE));
+        for alg in &algs {
+            assert!(solves(&case, &alg.full()), "{}", alg);
+        }
+        let mut shown: Vec<String> = algs.iter().map(|a| alg_to_string(&simplify(&a.full()))).collect();
+        let count = shown.len();
+        shown.dedup();
+        assert_eq!(shown.len(), count);
+    }
+
+    #[test]
+    fn check_masked_case() {
+        let yellow = Cube::default().color((1, 4));
+        let case = case_of(&parse_alg(SUNE).unwrap())
+            .masked(|(row, _), color| row >= 4 || color == yellow);
+        let mut algs = AlgSearch::new("<R,U>", 7).unwrap().run(&case);
+        sort_by_metric(&mut algs, Metric::Qtm);
+        assert!(algs.iter().any(|a| alg_to_string(&a.moves) == SUNE));
+        let unmasked = AlgSearch::new("<R,U>", 7).unwrap().run(&case_of(&parse_alg(SUNE).unwrap()));
+        assert!(algs.len() >= unmasked.len());
+        assert!(algs.windows(2).all(|w| w[0].count(Metric::Qtm) <= w[1].count(Metric::Qtm)));
+        assert!(algs.iter().all(|a| solves(&case, &a.full())));
+        assert!(!solves(&case, &parse_alg("R U R'").unwrap()));
+    }
+}
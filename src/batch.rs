@@ -0,0 +1,154 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::{Cube, cross, roux, thistlethwaite, zz};
+use crate::cross::CrossAnalysis;
+use crate::notation::{Move, parse_alg};
+use crate::roux::RouxSolution;
+use crate::thistlethwaite::ThistlethwaiteSolution;
+
+// Runs the solvers on many cubes at once. The pruning tables live in
+// statics, so every thread reads the same ones, and the first thread to
+// need a table builds (or loads) it while the others wait.
+
+pub trait BatchInput: Sync {
+    fn cube(&self) -> Option<Cube>;
+}
+
+impl BatchInput for Cube {
+    fn cube(&self) -> Option<Cube> {
+        Some(self.clone())
+    }
+}
+
+// Scrambles, None if some move does not parse
+impl BatchInput for &str {
+    fn cube(&self) -> Option<Cube> {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(self)?);
+        Some(cube)
+    }
+}
+
+impl BatchInput for String {
+    fn cube(&self) -> Option<Cube> {
+        self.as_str().cube()
+    }
+}
+
+pub type Progress<'a> = &'a (dyn Fn(usize, usize) + Sync);
+
+pub struct Batch<'a> {
+    // at least one, `new` sees to it
+    threads: usize,
+    // done and total, called after every item from the thread that did it
+    pub progress: Option<Progress<'a>>
+}
+
+impl Default for Batch<'_> {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Batch { threads, progress: None }
+    }
+}
+
+impl<'a> Batch<'a> {
+    pub fn new(threads: usize) -> Self {
+        Batch { threads: threads.max(1), progress: None }
+    }
+    pub fn with_progress(self, progress: Progress<'a>) -> Self {
+        Batch { progress: Some(progress), ..self }
+    }
+    // Results come in the order of the items, whatever thread did them
+    pub fn run<T: Sync, R: Send>(&self, items: &[T], work: impl Fn(&T) -> R + Sync) -> Vec<R> {
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(items.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() {
+                        break;
+                    }
+                    let result = work(&items[i]);
+                    results.lock().unwrap()[i] = Some(result);
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(progress) = self.progress {
+                        progress(done, items.len());
+                    }
+                });
+            }
+        });
+        results.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+    }
+    pub fn roux(&self, inputs: &[impl BatchInput]) -> Vec<Option<RouxSolution>> {
+        self.run(inputs, |input| roux::solve(&input.cube()?))
+    }
+    pub fn thistlethwaite(&self, inputs: &[impl BatchInput]) -> Vec<Option<ThistlethwaiteSolution>> {
+        self.run(inputs, |input| thistlethwaite::solve(&input.cube()?))
+    }
+    pub fn cross(&self, inputs: &[impl BatchInput]) -> Vec<Option<Vec<CrossAnalysis>>> {
+        self.run(inputs, |input| cross::analyse(&input.cube()?))
+    }
+    pub fn eoline(&self, inputs: &[impl BatchInput], rotation: &[Move]) -> Vec<Option<Vec<Move>>> {
+        self.run(inputs, |input| zz::eoline(&input.cube()?, rotation))
+    }
+    pub fn eocross(&self, inputs: &[impl BatchInput], rotation: &[Move]) -> Vec<Option<Vec<Move>>> {
+        self.run(inputs, |input| zz::eocross(&input.cube()?, rotation))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRAMBLES: [&str; 4] = [
+        "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F",
+        "R U R' F2 D",
+        "not a scramble",
+        "F R"
+    ];
+
+    #[test]
+    fn check_order() {
+        let items: Vec<usize> = (0..50).collect();
+        let calls = AtomicUsize::new(0);
+        let progress = |done: usize, total: usize| {
+            assert!(done <= total && total == 50);
+            calls.fetch_add(1, Ordering::Relaxed);
+        };
+        let squares = Batch::new(4).with_progress(&progress).run(&items, |&i| {
+            thread::sleep(std::time::Duration::from_micros((50 - i as u64) * 20));
+            i * i
+        });
+        assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<_>>());
+        assert_eq!(calls.load(Ordering::Relaxed), 50);
+        assert!(Batch::new(0).run(&[] as &[usize], |&i| i).is_empty());
+        assert_eq!(Batch::new(0).run(&[3], |&i| i + 1), [4]);
+    }
+
+    #[test]
+    fn check_eoline() {
+        let batch = Batch::new(3).eoline(&SCRAMBLES, &[]);
+        let single: Vec<_> = SCRAMBLES.iter().map(|s| zz::eoline(&s.cube()?, &[])).collect();
+        assert_eq!(batch, single);
+        assert_eq!(batch[2], None);
+        let cubes: Vec<Cube> = SCRAMBLES.iter().filter_map(|s| s.cube()).collect();
+        assert_eq!(Batch::default().eoline(&cubes, &[]).len(), 3);
+    }
+
+    #[test]
+    fn check_cross() {
+        let crosses = |found: &Option<Vec<CrossAnalysis>>| {
+            found.as_ref().map(|found| found.iter().map(|a| a.cross.clone()).collect::<Vec<_>>())
+        };
+        let batch = Batch::new(2).cross(&SCRAMBLES);
+        for (scramble, found) in SCRAMBLES.iter().zip(&batch).filter(|(_, found)| found.is_some()) {
+            assert_eq!(crosses(found), crosses(&cross::analyse_scramble(scramble)), "{}", scramble);
+        }
+        // batch input is strict, where the single scramble skips what it
+        // can not read
+        assert!(batch[2].is_none() && cross::analyse_scramble(SCRAMBLES[2]).is_some());
+    }
+}
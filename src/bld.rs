@@ -0,0 +1,261 @@
+use std::fmt;
+use crate::Cube;
+use crate::cubie::CubieCube;
+use crate::facelet::*;
+
+// Letters go face by face, U L F R B D, and clockwise on each face from
+// the top left corner or the top edge, like in Speffz
+const FACE_ORIGINS: [Facelet; 6] = [(0, 3), (3, 0), (3, 3), (3, 6), (3, 9), (6, 3)];
+const CORNER_STEPS: [Facelet; 4] = [(0, 0), (0, 2), (2, 2), (2, 0)];
+const EDGE_STEPS: [Facelet; 4] = [(0, 1), (1, 2), (2, 1), (1, 0)];
+
+const SPEFFZ: &str = "ABCDEFGHIJKLMNOPQRSTUVWX";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetterScheme {
+    pub corners: [char; 24],
+    pub edges: [char; 24]
+}
+
+impl Default for LetterScheme {
+    fn default() -> Self {
+        LetterScheme::new(SPEFFZ, SPEFFZ).unwrap()
+    }
+}
+
+impl LetterScheme {
+    // 24 letters each, in the order of the Speffz letters
+    pub fn new(corners: &str, edges: &str) -> Option<Self> {
+        Some(LetterScheme {
+            corners: corners.chars().collect::<Vec<_>>().try_into().ok()?,
+            edges: edges.chars().collect::<Vec<_>>().try_into().ok()?
+        })
+    }
+}
+
+// Buffer pieces as in CORNER_NAMES and EDGE_NAMES, the buffer sticker is
+// the U/D one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Buffers {
+    pub corner: usize,
+    pub edge: usize
+}
+
+pub const OLD_POCHMANN: Buffers = Buffers { corner: 2, edge: 0 };    // ULB, UR
+pub const THREE_STYLE: Buffers = Buffers { corner: 0, edge: 1 };     // URF, UF
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memo {
+    pub edges: Vec<char>,
+    pub corners: Vec<char>,
+    // pieces left in their slots the wrong way round, by the letter of the
+    // facelet that shows their U/D (or F/B) sticker
+    pub flipped_edges: Vec<char>,
+    pub twisted_corners: Vec<char>,
+    // odd targets on both, the edges take a swap of UF and UR
+    pub parity: bool
+}
+
+fn in_pairs(letters: &[char]) -> Vec<String> {
+    letters.chunks(2).map(|pair| pair.iter().collect()).collect()
+}
+
+impl Memo {
+    pub fn edge_pairs(&self) -> Vec<String> {
+        in_pairs(&self.edges)
+    }
+    pub fn corner_pairs(&self) -> Vec<String> {
+        in_pairs(&self.corners)
+    }
+}
+
+impl fmt::Display for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letters = |letters: &[char]| letters.iter().map(char::to_string).collect::<Vec<_>>().join(" ");
+        writeln!(f, "Edges: {}", self.edge_pairs().join(" "))?;
+        writeln!(f, "Corners: {}", self.corner_pairs().join(" "))?;
+        writeln!(f, "Flipped: {}", letters(&self.flipped_edges))?;
+        writeln!(f, "Twisted: {}", letters(&self.twisted_corners))?;
+        write!(f, "Parity: {}", if self.parity { "yes" } else { "no" })
+    }
+}
+
+// The facelets in the order of the letters
+fn letter_facelets(steps: &[Facelet; 4]) -> Vec<Facelet> {
+    FACE_ORIGINS.iter()
+        .flat_map(|&(row, col)| steps.iter().map(move |&(r, c)| (row + r, col + c)))
+        .collect()
+}
+
+// A sticker is a slot and one of its facelets, numbered like in
+// CORNER_FACELETS and EDGE_FACELETS
+struct Pieces<'a> {
+    facelets: Vec<Vec<Facelet>>,
+    letters: Vec<Facelet>,
+    scheme: &'a [char; 24],
+    // the piece and the twist in each slot
+    slots: Vec<(usize, usize)>
+}
+
+impl<'a> Pieces<'a> {
+    fn edges(scheme: &'a LetterScheme, slots: Vec<(usize, usize)>) -> Self {
+        Pieces {
+            facelets: EDGE_FACELETS.iter().map(|e| e.to_vec()).collect(),
+            letters: letter_facelets(&EDGE_STEPS),
+            scheme: &scheme.edges,
+            slots
+        }
+    }
+    fn corners(scheme: &'a LetterScheme, slots: Vec<(usize, usize)>) -> Self {
+        Pieces {
+            facelets: CORNER_FACELETS.iter().map(|c| c.to_vec()).collect(),
+            letters: letter_facelets(&CORNER_STEPS),
+            scheme: &scheme.corners,
+            slots
+        }
+    }
+    fn letter(&self, (slot, index): (usize, usize)) -> char {
+        let facelet = self.facelets[slot][index];
+        self.scheme[self.letters.iter().position(|&f| f == facelet).unwrap()]
+    }
+    // the sticker, where what lies on the given one belongs
+    fn target(&self, (slot, index): (usize, usize)) -> (usize, usize) {
+        let size = self.facelets[slot].len();
+        let (piece, twist) = self.slots[slot];
+        (piece, (index + size - twist) % size)
+    }
+    // Targets from the buffer, and the pieces left twisted in place. When
+    // the buffer comes back home, the cycle is broken into the first
+    // piece left in the order of the letters.
+    fn trace(&self, buffer: usize) -> (Vec<char>, Vec<char>) {
+        let count = self.slots.len();
+        let mut done: Vec<bool> = (0..count).map(|slot| slot == buffer || self.slots[slot].0 == slot).collect();
+        let mut twisted: Vec<char> = (0..count)
+            .filter(|&slot| slot != buffer && self.slots[slot].0 == slot && self.slots[slot].1 != 0)
+            .map(|slot| self.letter((slot, self.slots[slot].1)))
+            .collect();
+        let mut targets = Vec::new();
+        let size = self.facelets[buffer].len();
+        // where the reference sticker of the buffer piece ends up, every
+        // cycle that closes on another sticker than it broke into turns it
+        let mut buffer_twist = 0;
+        let mut start = (buffer, 0);
+        let mut current = start;
+        loop {
+            let target = self.target(current);
+            if target.0 != start.0 {
+                targets.push(self.letter(target));
+                done[target.0] = true;
+                current = target;
+                continue;
+            }
+            if start.0 != buffer {
+                targets.push(self.letter(target));
+            }
+            buffer_twist = (buffer_twist + start.1 + size - target.1) % size;
+            let next = self.letters.iter()
+                .filter_map(|&f| (0..count).find_map(|slot| {
+                    self.facelets[slot].iter().position(|&g| g == f).map(|index| (slot, index))
+                }))
+                .find(|&(slot, _)| !done[slot]);
+            let Some(next) = next else {
+                break;
+            };
+            targets.push(self.letter(next));
+            done[next.0] = true;
+            start = next;
+            current = next;
+        }
+        if buffer_twist != 0 {
+            twisted.push(self.letter((buffer, buffer_twist)));
+        }
+        (targets, twisted)
+    }
+}
+
+// The memo of the cube against its own centres, None if the stickers
+// are not a cube
+pub fn memo(cube: &Cube, scheme: &LetterScheme, buffers: Buffers) -> Option<Memo> {
+    let cubie = CubieCube::from_cube(cube)?;
+    let edges = Pieces::edges(scheme, (0..12).map(|s| (cubie.ep[s] as usize, cubie.eo[s] as usize)).collect());
+    let corners = Pieces::corners(scheme, (0..8).map(|s| (cubie.cp[s] as usize, cubie.co[s] as usize)).collect());
+    let (edge_targets, flipped_edges) = edges.trace(buffers.edge);
+    let (corner_targets, twisted_corners) = corners.trace(buffers.corner);
+    Some(Memo {
+        parity: corner_targets.len() % 2 == 1,
+        edges: edge_targets,
+        corners: corner_targets,
+        flipped_edges,
+        twisted_corners
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+    // URF and UBR twisted against each other
+    const TWISTS: &str = "R' D' R D R' D' R D U R' D' R D R' D' R D R' D' R D R' D' R D U'";
+
+    // Swaps the buffer with every target, like Old Pochmann does, then puts
+    // the flipped or twisted pieces right
+    fn execute(cube: &mut Cube, pieces: &Pieces, buffer: usize, targets: &[char], twisted: &[char]) {
+        let solved = Cube::default();
+        let find = |letter: &char| {
+            let facelet = pieces.letters[pieces.scheme.iter().position(|l| l == letter).unwrap()];
+            (0..pieces.facelets.len())
+                .find_map(|slot| pieces.facelets[slot].iter().position(|&f| f == facelet).map(|i| (slot, i)))
+                .unwrap()
+        };
+        let size = pieces.facelets[buffer].len();
+        for target in targets {
+            let (slot, index) = find(target);
+            for k in 0..size {
+                let (a, b) = (pieces.facelets[buffer][k], pieces.facelets[slot][(index + k) % size]);
+                let color = cube.color(a);
+                cube.scan[a.0][a.1] = cube.color(b);
+                cube.scan[b.0][b.1] = color;
+            }
+        }
+        for letter in twisted {
+            let (slot, index) = find(letter);
+            assert_ne!(index, 0);
+            assert_eq!(cube.color(pieces.facelets[slot][index]), solved.color(pieces.facelets[slot][0]));
+            for &f in &pieces.facelets[slot] {
+                cube.scan[f.0][f.1] = solved.color(f);
+            }
+        }
+    }
+
+    #[test]
+    fn check_u_turn() {
+        let found = memo(&Cube::from("U"), &LetterScheme::default(), OLD_POCHMANN).unwrap();
+        assert_eq!(found.edges, ['A', 'D', 'C']);
+        assert_eq!(found.corners, ['D', 'C', 'B']);
+        assert!(found.parity);
+        assert_eq!(found.edge_pairs(), ["AD", "C"]);
+        let solved = memo(&Cube::default(), &LetterScheme::default(), THREE_STYLE).unwrap();
+        assert!(solved.edges.is_empty() && solved.corners.is_empty() && !solved.parity);
+    }
+
+    #[test]
+    fn check_execute() {
+        let scheme = LetterScheme::new("abcdefghijklmnopqrstuvwx", SPEFFZ).unwrap();
+        let scrambles = [SCRAMBLE, "R U R' U'", "M2 U M2 U2 M2 U M2", "R' U R' U' R' U' R' U R U R2", "F R U R' U' F'", "U R U' L' U R' U' L", TWISTS];
+        for scramble in scrambles {
+            for buffers in [OLD_POCHMANN, THREE_STYLE] {
+                let mut cube = Cube::from(scramble);
+                let found = memo(&cube, &scheme, buffers).unwrap();
+                assert_eq!(found.parity, found.edges.len() % 2 == 1, "{}", scramble);
+                execute(&mut cube, &Pieces::edges(&scheme, vec![]), buffers.edge, &found.edges, &found.flipped_edges);
+                execute(&mut cube, &Pieces::corners(&scheme, vec![]), buffers.corner, &found.corners, &found.twisted_corners);
+                assert_eq!(cube, Cube::default(), "{} {}", scramble, found);
+            }
+        }
+        let twisted = memo(&Cube::from(TWISTS), &scheme, OLD_POCHMANN).unwrap();
+        assert!(twisted.corners.is_empty() && twisted.edges.is_empty());
+        assert_eq!(twisted.twisted_corners, ['j', 'q']);
+        assert!(LetterScheme::new("ABC", SPEFFZ).is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::facelet::*;
+
+// Blocks are the corners and edges in a box of cubies. A block is solved
+// when its pieces sit like in the solved cube held some way, so it may be
+// off from the centres or from the rest of the cube.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    Pair,
+    Square,
+    Block222,
+    Block123,
+    Block223,
+    F2lMinusSlot
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BlockKind::Pair => "1x1x2",
+            BlockKind::Square => "1x2x2",
+            BlockKind::Block222 => "2x2x2",
+            BlockKind::Block123 => "1x2x3",
+            BlockKind::Block223 => "2x2x3",
+            BlockKind::F2lMinusSlot => "F2L-1"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub kind: BlockKind,
+    // the slots the block takes, as in CORNER_NAMES and EDGE_NAMES
+    pub corners: Vec<&'static str>,
+    pub edges: Vec<&'static str>,
+    // every colour on the block, in the order of its stickers
+    pub colors: Vec<Stiker>
+}
+
+struct Location {
+    kind: BlockKind,
+    corners: Vec<usize>,
+    edges: Vec<usize>
+}
+
+// U, L, F, R, B, D as x, y, z steps away from the core
+const FACE_STEPS: [[i8; 3]; 6] = [[0, 1, 0], [-1, 0, 0], [0, 0, 1], [1, 0, 0], [0, 0, -1], [0, -1, 0]];
+
+fn position(facelets: &[Facelet]) -> [i8; 3] {
+    let mut position = [0; 3];
+    for &f in facelets {
+        for (axis, step) in FACE_STEPS[face_of(f)].iter().enumerate() {
+            position[axis] += step;
+        }
+    }
+    position
+}
+
+// Every box of cubies, given by the first and the last step on each axis
+fn boxes() -> Vec<[(i8, i8); 3]> {
+    let ranges: Vec<(i8, i8)> = (-1..=1).flat_map(|lo| (lo..=1).map(move |hi| (lo, hi))).collect();
+    let mut boxes = Vec::new();
+    for &x in &ranges {
+        for &y in &ranges {
+            for &z in &ranges {
+                boxes.push([x, y, z]);
+            }
+        }
+    }
+    boxes
+}
+
+fn locations() -> &'static [Location] {
+    static LOCATIONS: OnceLock<Vec<Location>> = OnceLock::new();
+    LOCATIONS.get_or_init(|| {
+        let corners: Vec<[i8; 3]> = CORNER_FACELETS.iter().map(|c| position(c)).collect();
+        let edges: Vec<[i8; 3]> = EDGE_FACELETS.iter().map(|e| position(e)).collect();
+        let mut locations = Vec::new();
+        for bounds in boxes() {
+            let inside = |p: &[i8; 3]| (0..3).all(|axis| (bounds[axis].0..=bounds[axis].1).contains(&p[axis]));
+            let in_corners: Vec<usize> = (0..8).filter(|&c| inside(&corners[c])).collect();
+            let in_edges: Vec<usize> = (0..12).filter(|&e| inside(&edges[e])).collect();
+            let mut size = bounds.map(|(lo, hi)| hi - lo + 1);
+            size.sort();
+            let kind = match (size, in_corners.len(), in_edges.len()) {
+                ([1, 1, 2], 1, 1) => BlockKind::Pair,
+                ([1, 2, 2], 1, 2) => BlockKind::Square,
+                ([2, 2, 2], 1, 3) => BlockKind::Block222,
+                ([1, 2, 3], 2, 3) => BlockKind::Block123,
+                ([2, 2, 3], 2, 5) => BlockKind::Block223,
+                ([2, 3, 3], 4, 8) => {
+                    // the first two layers, less the edge of a slot and the
+                    // corner under it
+                    let thin = (0..3).find(|&axis| bounds[axis].1 - bounds[axis].0 == 1).unwrap();
+                    for &slot in in_edges.iter().filter(|&&e| edges[e][thin] == 0) {
+                        let mut corner = edges[slot];
+                        corner[thin] = bounds[thin].0 + bounds[thin].1;
+                        locations.push(Location {
+                            kind: BlockKind::F2lMinusSlot,
+                            corners: in_corners.iter().copied().filter(|&c| corners[c] != corner).collect(),
+                            edges: in_edges.iter().copied().filter(|&e| e != slot).collect()
+                        });
+                    }
+                    continue;
+                }
+                _ => continue
+            };
+            locations.push(Location { kind, corners: in_corners, edges: in_edges });
+        }
+        locations
+    })
+}
+
+// Every solved block of the cube, the small ones inside bigger ones too
+pub fn find_blocks(cube: &Cube) -> Vec<Block> {
+    let scheme = cube.centre_colors();
+    let mut blocks = Vec::new();
+    for location in locations() {
+        let facelets: Vec<Facelet> = location.corners.iter().flat_map(|&c| CORNER_FACELETS[c])
+            .chain(location.edges.iter().flat_map(|&e| EDGE_FACELETS[e]))
+            .collect();
+        let solved = rotation_perms().iter().any(|perm| {
+            facelets.iter().all(|&f| cube.color(f) == scheme[face_of(facelet(perm[sticker(f) as usize]))])
+        });
+        if !solved {
+            continue;
+        }
+        let mut colors = Vec::new();
+        for &f in &facelets {
+            if !colors.contains(&cube.color(f)) {
+                colors.push(cube.color(f));
+            }
+        }
+        blocks.push(Block {
+            kind: location.kind,
+            corners: location.corners.iter().map(|&c| CORNER_NAMES[c]).collect(),
+            edges: location.edges.iter().map(|&e| EDGE_NAMES[e]).collect(),
+            colors
+        });
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    fn counts(setup: &str) -> Vec<usize> {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(setup).unwrap());
+        let blocks = find_blocks(&cube);
+        [BlockKind::Pair, BlockKind::Square, BlockKind::Block222, BlockKind::Block123, BlockKind::Block223, BlockKind::F2lMinusSlot]
+            .iter()
+            .map(|&kind| blocks.iter().filter(|b| b.kind == kind).count())
+            .collect()
+    }
+
+    #[test]
+    fn check_solved_blocks() {
+        assert_eq!(counts(""), [24, 24, 8, 24, 12, 24]);
+        assert_eq!(counts("x y2"), counts(""));
+        // the D layer is only off from the rest, its own blocks stay
+        assert_eq!(counts("D"), [20, 16, 4, 12, 4, 4]);
+    }
+
+    #[test]
+    fn check_found_blocks() {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg("R U R' U'").unwrap());
+        let blocks = find_blocks(&cube);
+        let f2l: Vec<&Block> = blocks.iter().filter(|b| b.kind == BlockKind::F2lMinusSlot).collect();
+        assert!(f2l.iter().any(|b| !b.corners.contains(&"DFR") && !b.edges.contains(&"FR") && b.edges.contains(&"DF")));
+        let roux = blocks.iter().find(|b| b.kind == BlockKind::Block123 && b.corners == ["DLF", "DBL"]).unwrap();
+        assert_eq!(roux.edges, ["DL", "FL", "BL"]);
+        assert_eq!(roux.colors, [Stiker::W, Stiker::O, Stiker::B, Stiker::G]);
+        let mut scrambled = Cube::default();
+        scrambled.apply_alg(&parse_alg("R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F").unwrap());
+        assert!(find_blocks(&scrambled).iter().all(|b| b.kind == BlockKind::Pair));
+    }
+}
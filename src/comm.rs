@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::facelet::*;
+use crate::notation::{Layer::{self, *}, Metric, Move, alg_to_string, invert, simplify};
+use crate::search::is_redundant;
+
+// Commutators are [A, B], where B is a single turn and A a single turn or
+// an insertion like R U R', in either order, with up to MAX_SETUP turns
+// of setup around them.
+
+pub const MAX_SETUP: usize = 2;
+
+const LAYERS: [Layer; 9] = [U, D, L, R, F, B, M, E, S];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commutator {
+    pub setup: Vec<Move>,
+    pub a: Vec<Move>,
+    pub b: Vec<Move>
+}
+
+impl Commutator {
+    pub fn moves(&self) -> Vec<Move> {
+        let inner = [&self.a[..], &self.b, &invert(&self.a), &invert(&self.b)].concat();
+        simplify(&[&self.setup[..], &inner, &invert(&self.setup)].concat())
+    }
+    pub fn count(&self, metric: Metric) -> usize {
+        metric.count(&self.moves())
+    }
+    // Lower is easier: R and U are the easiest turns, B and the E and S
+    // slices the hardest, half turns take a bit more
+    pub fn ergonomics(&self) -> f64 {
+        self.moves().iter().map(|m| {
+            let cost = match m.layer {
+                R | U => 1.0,
+                M => 1.2,
+                D | L | F => 1.5,
+                _ => 2.5
+            };
+            if m.amount == 2 { cost * 1.5 } else { cost }
+        }).sum()
+    }
+}
+
+impl fmt::Display for Commutator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = format!("[{}, {}]", alg_to_string(&self.a), alg_to_string(&self.b));
+        if self.setup.is_empty() {
+            write!(f, "{}", inner)
+        } else {
+            write!(f, "[{}: {}]", alg_to_string(&self.setup), inner)
+        }
+    }
+}
+
+fn perm(alg: &[Move]) -> Vec<u8> {
+    let mut stickers: Vec<u8> = (0..STICKERS as u8).collect();
+    for &m in alg {
+        apply_to_stickers(&mut stickers, m);
+    }
+    stickers
+}
+
+fn sequences(max_length: usize) -> Vec<Vec<Move>> {
+    let turns = Move::all(&LAYERS);
+    let mut result: Vec<Vec<Move>> = vec![vec![]];
+    let mut last = result.clone();
+    for _ in 0..max_length {
+        last = last.iter()
+            .flat_map(|seq| turns.iter()
+                .filter(|&&m| !is_redundant(seq.last().copied(), m))
+                .map(move |&m| [&seq[..], &[m]].concat()))
+            .collect();
+        result.extend(last.iter().cloned());
+    }
+    result
+}
+
+// Every commutator of the form above, that is a cycle of three pieces,
+// with where each sticker goes
+fn three_cycles() -> &'static [(Commutator, Vec<u8>)] {
+    static CYCLES: OnceLock<Vec<(Commutator, Vec<u8>)>> = OnceLock::new();
+    CYCLES.get_or_init(|| {
+        let turns = Move::all(&LAYERS);
+        let mut parts: Vec<Vec<Move>> = turns.iter().map(|&m| vec![m]).collect();
+        for &x in &turns {
+            for &y in turns.iter().filter(|&&y| y.layer.axis() != x.layer.axis()) {
+                parts.push(vec![x, y, x.inverse()]);
+            }
+        }
+        let mut cycles = Vec::new();
+        for a in &parts {
+            // an insertion around a turn of the other part's layer is a
+            // longer way to write a shorter commutator
+            for &b in turns.iter().filter(|b| a.len() == 1 || b.layer != a[0].layer) {
+                for (a, b) in [(a.clone(), vec![b]), (vec![b], a.clone())] {
+                    let comm = Commutator { setup: vec![], a, b };
+                    let stickers = perm(&comm.moves());
+                    let moved = stickers.iter().enumerate().filter(|&(i, &s)| i != s as usize).count();
+                    if moved == 6 || moved == 9 {
+                        cycles.push((comm, stickers));
+                    }
+                }
+            }
+        }
+        cycles
+    })
+}
+
+// Stickers are named by their face first, like UF, LU or FUR
+fn parse_sticker(name: &str) -> Option<(Facelet, usize)> {
+    let faces: Vec<usize> = name.chars().map(|c| "ULFRBD".find(c)).collect::<Option<_>>()?;
+    let pieces: Vec<&[Facelet]> = match faces.len() {
+        2 => EDGE_FACELETS.iter().map(|e| &e[..]).collect(),
+        3 => CORNER_FACELETS.iter().map(|c| &c[..]).collect(),
+        _ => return None
+    };
+    pieces.iter().enumerate().find_map(|(piece, facelets)| {
+        let mut piece_faces: Vec<usize> = facelets.iter().map(|&f| face_of(f)).collect();
+        let facelet = *facelets.iter().find(|&&f| face_of(f) == faces[0])?;
+        piece_faces.sort();
+        let mut wanted = faces.clone();
+        wanted.sort();
+        (piece_faces == wanted).then_some((facelet, piece))
+    })
+}
+
+// The pieces of the stickers, each facelet with the one that goes where
+// it goes, solved everywhere else
+fn cycled(stickers: &[Facelet]) -> Cube {
+    let solved = Cube::default();
+    let mut cube = Cube::default();
+    let pieces: Vec<&[Facelet]> = if stickers.iter().all(|f| EDGE_FACELETS.iter().any(|e| e.contains(f))) {
+        EDGE_FACELETS.iter().map(|e| &e[..]).collect()
+    } else {
+        CORNER_FACELETS.iter().map(|c| &c[..]).collect()
+    };
+    let around = |f: Facelet| -> Vec<Facelet> {
+        let piece = pieces.iter().find(|p| p.contains(&f)).unwrap();
+        let start = piece.iter().position(|&g| g == f).unwrap();
+        (0..piece.len()).map(|k| piece[(start + k) % piece.len()]).collect()
+    };
+    for i in 0..stickers.len() {
+        let from = around(stickers[i]);
+        let to = around(stickers[(i + 1) % stickers.len()]);
+        for (&from, &to) in from.iter().zip(&to) {
+            cube.scan[to.0][to.1] = solved.color(from);
+        }
+    }
+    cube
+}
+
+// Checks the commutator on the cube, it does the cycle and nothing else
+pub fn performs(comm: &Commutator, cycle: &str) -> bool {
+    let Some(stickers) = parse_cycle(cycle) else {
+        return false;
+    };
+    let mut cube = Cube::default();
+    cube.apply_alg(&comm.moves());
+    cube == cycled(&stickers)
+}
+
+fn parse_cycle(cycle: &str) -> Option<Vec<Facelet>> {
+    let stickers: Vec<(Facelet, usize)> = cycle.replace("->", " ").split_whitespace()
+        .map(parse_sticker)
+        .collect::<Option<_>>()?;
+    let pieces: HashSet<usize> = stickers.iter().map(|&(_, piece)| piece).collect();
+    let same_kind = stickers.iter().all(|&(f, _)| EDGE_FACELETS.iter().any(|e| e.contains(&f)))
+        || stickers.iter().all(|&(f, _)| CORNER_FACELETS.iter().any(|c| c.contains(&f)));
+    (stickers.len() == 3 && pieces.len() == 3 && same_kind).then(|| stickers.iter().map(|&(f, _)| f).collect())
+}
+
+// The commutators for a cycle like "UF -> UB -> LU", fewest slice turn
+// metric moves first, then the easiest. None if the cycle does not parse.
+pub fn find_commutators(cycle: &str) -> Option<Vec<Commutator>> {
+    let stickers: Vec<u8> = parse_cycle(cycle)?.into_iter().map(sticker).collect();
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for setup in sequences(MAX_SETUP) {
+        let moved = perm(&setup);
+        let targets: Vec<u8> = stickers.iter().map(|&s| moved[s as usize]).collect();
+        // a last setup turn, that leaves the stickers where they are, does
+        // nothing the shorter setup does not
+        if !setup.is_empty() {
+            let before = perm(&setup[..setup.len() - 1]);
+            if stickers.iter().all(|&s| moved[s as usize] == before[s as usize]) {
+                continue;
+            }
+        }
+        for (comm, cycle_perm) in three_cycles() {
+            let fits = (0..3).all(|i| cycle_perm[targets[i] as usize] == targets[(i + 1) % 3]);
+            if !fits {
+                continue;
+            }
+            let comm = Commutator { setup: setup.clone(), ..comm.clone() };
+            if seen.insert(alg_to_string(&comm.moves())) && performs(&comm, cycle) {
+                found.push(comm);
+            }
+        }
+    }
+    found.sort_by(|a, b| {
+        (a.count(Metric::Stm), a.ergonomics()).partial_cmp(&(b.count(Metric::Stm), b.ergonomics())).unwrap()
+    });
+    Some(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    #[test]
+    fn check_edges() {
+        let found = find_commutators("UF -> UB -> DB").unwrap();
+        assert_eq!(found[0].to_string(), "[M, U2]");
+        assert!(found.windows(2).all(|w| w[0].count(Metric::Stm) <= w[1].count(Metric::Stm)));
+        let found = find_commutators("UF -> UB -> LU").unwrap();
+        assert!(!found.is_empty() && found[0].count(Metric::Stm) <= 8);
+        assert!(found.iter().all(|c| performs(c, "UF UB LU")));
+        assert!(!performs(&found[0], "UF LU UB"));
+    }
+
+    #[test]
+    fn check_corners() {
+        let found = find_commutators("UFR RFD FDL").unwrap();
+        assert_eq!(found[0].to_string(), "[R U R', D]");
+        assert_eq!(found[0].count(Metric::Stm), 8);
+        assert!(found.iter().all(|c| c.setup.len() <= MAX_SETUP && performs(c, "UFR RFD FDL")));
+        let comm = Commutator {
+            setup: parse_alg("R2").unwrap(),
+            a: parse_alg("R D R'").unwrap(),
+            b: parse_alg("U2").unwrap()
+        };
+        assert_eq!(comm.to_string(), "[R2: [R D R', U2]]");
+        assert_eq!(alg_to_string(&comm.moves()), "R' D R' U2 R D' R' U2 R2");
+        assert!(find_commutators("UF UB").is_none());
+        assert!(find_commutators("UF UFR UB").is_none());
+        assert!(find_commutators("UF FU UB").is_none());
+    }
+}
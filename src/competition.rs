@@ -0,0 +1,317 @@
+use std::fmt;
+use std::io;
+use crate::json::{ToJson, write_object};
+use crate::notation::{Layer::*, Move, alg_to_string};
+use crate::random::{ChaCha, Random};
+use crate::scramble::{random_state, random_state_222};
+
+pub const SCRAMBLES_PER_GROUP: usize = 5;
+pub const EXTRAS_PER_GROUP: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Cube333,
+    Cube222,
+    OneHanded,
+    Blindfolded
+}
+
+impl Event {
+    // as in the results of the WCA
+    pub fn id(self) -> &'static str {
+        match self {
+            Event::Cube333 => "333",
+            Event::Cube222 => "222",
+            Event::OneHanded => "333oh",
+            Event::Blindfolded => "333bf"
+        }
+    }
+    pub fn scramble<R: Random>(self, random: &mut R) -> Vec<Move> {
+        match self {
+            Event::Cube333 | Event::OneHanded => random_state(random),
+            Event::Cube222 => random_state_222(random),
+            Event::Blindfolded => {
+                // wide turns leave the cube held any of the 24 ways, so
+                // the solver can not take the centres as given
+                let mut scramble = random_state(random);
+                let top = [None, Some(Move::new(Rw, 1)), Some(Move::new(Rw, 2)), Some(Move::new(Rw, 3)),
+                    Some(Move::new(Fw, 1)), Some(Move::new(Fw, 3))];
+                scramble.extend(top[random.below(6) as usize]);
+                let turn = random.below(4) as u8;
+                if turn > 0 {
+                    scramble.push(Move::new(Uw, turn));
+                }
+                scramble
+            }
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Event::Cube333 => "3x3",
+            Event::Cube222 => "2x2",
+            Event::OneHanded => "OH",
+            Event::Blindfolded => "BLD"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    // rounds count from 1, groups are A, B, C...
+    pub round: usize,
+    pub group: char,
+    pub scrambles: Vec<Vec<Move>>,
+    pub extras: Vec<Vec<Move>>
+}
+
+impl Group {
+    // Every scramble with a label like "333 R1 A 3" or "333 R1 A E1"
+    pub fn labelled(&self, event: Event) -> Vec<(String, &[Move])> {
+        let prefix = format!("{} R{} {}", event.id(), self.round, self.group);
+        let scrambles = self.scrambles.iter().enumerate()
+            .map(|(i, s)| (format!("{} {}", prefix, i + 1), s.as_slice()));
+        let extras = self.extras.iter().enumerate()
+            .map(|(i, s)| (format!("{} E{}", prefix, i + 1), s.as_slice()));
+        scrambles.chain(extras).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventScrambles {
+    pub event: Event,
+    pub groups: Vec<Group>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub seed: u64,
+    pub events: Vec<EventScrambles>
+}
+
+// The randomness of an event comes from the seed and the event alone, so
+// adding an event leaves the scrambles of the others as they were. The
+// scrambles are only as hard to guess as the seed, official packages take
+// it from random_seed.
+fn event_random(seed: u64, event: Event) -> ChaCha {
+    ChaCha::new(sha256(format!("{} {}", seed, event.id()).as_bytes()))
+}
+
+pub fn random_seed() -> io::Result<u64> {
+    Ok(ChaCha::from_entropy()?.next_u64())
+}
+
+// The number of groups of each round for every event
+pub fn package(seed: u64, schedule: &[(Event, &[usize])]) -> Package {
+    let events = schedule.iter().map(|&(event, rounds)| {
+        let mut random = event_random(seed, event);
+        let mut groups = Vec::new();
+        for (round, &count) in rounds.iter().enumerate() {
+            for group in (b'A'..).take(count) {
+                let mut draw = |n| (0..n).map(|_| event.scramble(&mut random)).collect();
+                let scrambles = draw(SCRAMBLES_PER_GROUP);
+                let extras = draw(EXTRAS_PER_GROUP);
+                groups.push(Group { round: round + 1, group: group as char, scrambles, extras });
+            }
+        }
+        EventScrambles { event, groups }
+    }).collect();
+    Package { seed, events }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrambleHash {
+    pub label: String,
+    pub sha256: String
+}
+
+impl Package {
+    // The hash covers the label with the scramble, a scramble moved to
+    // another group does not check out either
+    pub fn hashes(&self) -> Vec<ScrambleHash> {
+        self.events.iter()
+            .flat_map(|e| e.groups.iter().flat_map(|g| g.labelled(e.event)))
+            .map(|(label, scramble)| {
+                let sha256 = hex(&sha256(format!("{}: {}", label, alg_to_string(scramble)).as_bytes()));
+                ScrambleHash { label, sha256 }
+            })
+            .collect()
+    }
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Seed: {}\n", self.seed);
+        for hash in self.hashes() {
+            summary.push_str(&format!("{}  {}\n", hash.sha256, hash.label));
+        }
+        summary
+    }
+}
+
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Seed: {}", self.seed)?;
+        for event in &self.events {
+            for group in &event.groups {
+                writeln!(f)?;
+                writeln!(f, "{} Round {} Group {}", event.event, group.round, group.group)?;
+                for (i, scramble) in group.scrambles.iter().enumerate() {
+                    writeln!(f, "{}. {}", i + 1, alg_to_string(scramble))?;
+                }
+                for (i, scramble) in group.extras.iter().enumerate() {
+                    writeln!(f, "E{}. {}", i + 1, alg_to_string(scramble))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ToJson for Group {
+    fn write_json(&self, out: &mut String) {
+        let algs = |scrambles: &[Vec<Move>]| scrambles.iter().map(|s| alg_to_string(s)).collect::<Vec<_>>();
+        write_object(out, &[
+            ("round", &self.round),
+            ("group", &self.group),
+            ("scrambles", &algs(&self.scrambles)),
+            ("extras", &algs(&self.extras))
+        ]);
+    }
+}
+
+impl ToJson for EventScrambles {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[
+            ("event", &self.event.id()),
+            ("name", &self.event.to_string()),
+            ("groups", &self.groups)
+        ]);
+    }
+}
+
+impl ToJson for ScrambleHash {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[("label", &self.label), ("sha256", &self.sha256)]);
+    }
+}
+
+impl ToJson for Package {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[
+            ("seed", &self.seed),
+            ("events", &self.events),
+            ("hashes", &self.hashes())
+        ]);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// SHA-256 as in FIPS 180-4, written out to keep to one dependency
+pub(crate) fn sha256(message: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+    ];
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+    ];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend((message.len() as u64 * 8).to_be_bytes());
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    let mut digest = [0; 32];
+    for (i, s) in state.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Cube;
+    use crate::cubie::CubieCube;
+
+    #[test]
+    fn check_sha256() {
+        assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        let long = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(hex(&sha256(long)), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    }
+
+    #[test]
+    fn check_package() {
+        let schedule: [(Event, &[usize]); 2] = [(Event::Cube222, &[2, 1]), (Event::Blindfolded, &[1])];
+        let first = package(42, &schedule);
+        assert_eq!(first, package(42, &schedule));
+        assert_ne!(first, package(43, &schedule));
+        assert_ne!(random_seed().unwrap(), random_seed().unwrap());
+        // the 2x2 scrambles do not depend on the other events
+        assert_eq!(package(42, &schedule[..1]).events[0], first.events[0]);
+        let groups: Vec<(usize, char)> = first.events[0].groups.iter().map(|g| (g.round, g.group)).collect();
+        assert_eq!(groups, [(1, 'A'), (1, 'B'), (2, 'A')]);
+        for group in first.events.iter().flat_map(|e| &e.groups) {
+            assert_eq!((group.scrambles.len(), group.extras.len()), (5, 2));
+        }
+        let two = &first.events[0].groups[0].scrambles[0];
+        assert!((4..=11).contains(&two.len()) && two.iter().all(|m| [R, U, F].contains(&m.layer)));
+        let hashes = first.hashes();
+        assert_eq!(hashes.len(), 28);
+        assert_eq!(hashes[6].label, "222 R1 A E2");
+        assert_eq!(hashes[27].label, "333bf R1 A E2");
+        let mut cube = Cube::default();
+        cube.apply_alg(&first.events[1].groups[0].scrambles[0]);
+        assert!(CubieCube::from_cube(&cube).unwrap().is_solvable());
+        let text = first.to_string();
+        assert!(text.starts_with("Seed: 42\n\n2x2 Round 1 Group A\n1. "));
+        assert!(text.contains("\nBLD Round 1 Group A\n") && text.contains("\nE2. "));
+        assert!(first.summary().lines().nth(1).unwrap().ends_with("  222 R1 A 1"));
+        let json = first.to_json();
+        assert!(json.starts_with(r#"{"seed":42,"events":[{"event":"222","name":"2x2","groups":[{"round":1,"group":"A","scrambles":[""#));
+        assert!(json.contains(&format!(r#"{{"label":"333bf R1 A 1","sha256":"{}"}}"#, hashes[21].sha256)));
+    }
+}
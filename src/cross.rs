@@ -0,0 +1,183 @@
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::facelet::*;
+use crate::notation::{Layer::*, Move, parse_alg};
+use crate::search::{Pruning, Search};
+use crate::tables::cached;
+
+pub(crate) const CROSS: [usize; 4] = [5, 7, 4, 6]; // DF DB DR DL
+const PAIR_EDGE: usize = 8;                 // FR
+const PAIR_CORNER: usize = 4;               // DFR
+
+// XCross is only looked for up to this many moves over the cross
+pub const XCROSS_MARGIN: usize = 3;
+
+// Rotations that bring each face to the bottom, faces as in CENTER_FACELETS
+const BOTTOM_ROTATIONS: [&str; 6] = ["x2", "z'", "x'", "z", "x", ""];
+
+// The slot, that y turns bring to FR, in the frame before them
+pub(crate) const XCROSS_SLOTS: [&str; 4] = ["FR", "BR", "BL", "FL"];
+
+#[derive(Debug, Clone)]
+pub struct XCross {
+    pub slot: &'static str,
+    pub moves: Vec<Move>
+}
+
+#[derive(Debug, Clone)]
+pub struct CrossAnalysis {
+    pub bottom: Stiker,
+    pub rotation: Vec<Move>,
+    pub cross: Vec<Move>,
+    pub xcross: Option<XCross>
+}
+
+pub(crate) fn moves() -> Vec<Move> {
+    Move::all(&[U, D, L, R, F, B])
+}
+
+pub(crate) fn tables() -> &'static [Pruning; 2] {
+    static TABLES: OnceLock<[Pruning; 2]> = OnceLock::new();
+    TABLES.get_or_init(|| cached("cross", || {
+        let goal = homes();
+        [
+            Pruning::new(vec![0, 1, 2, 3], &goal, &moves(), 20),
+            Pruning::new(vec![0, 2, 4, 5], &goal, &moves(), 20)
+        ]
+    }))
+}
+
+pub(crate) fn homes() -> Vec<u8> {
+    let mut homes: Vec<u8> = CROSS.iter().map(|&e| edge_home(e)).collect();
+    homes.extend([edge_home(PAIR_EDGE), corner_home(PAIR_CORNER)]);
+    homes
+}
+
+// cross edges, then the FR pair, coloured after the centres of the view
+fn stickers(view: &Cube) -> Option<Vec<u8>> {
+    let scheme = view.centre_colors();
+    let mut stickers = Vec::new();
+    for edge in CROSS.iter().chain(&[PAIR_EDGE]) {
+        stickers.push(view.edge_sticker_in(*edge, &scheme)?);
+    }
+    stickers.push(view.corner_sticker_in(PAIR_CORNER, &scheme)?);
+    Some(stickers)
+}
+
+fn cross(view: &Cube, limit: usize) -> Option<Vec<Move>> {
+    let start = stickers(view)?;
+    let goal = homes();
+    Search {
+        moves: &moves(),
+        tables: &tables()[..1],
+        goal: &|s| s[..4] == goal[..4]
+    }.first(&start, limit)
+}
+
+// The fewest moves to a cross of any colour, None if every colour takes
+// more than `limit`
+pub fn shortest_cross(cube: &Cube, limit: usize) -> Option<usize> {
+    let mut best = None;
+    for rotation in BOTTOM_ROTATIONS {
+        let mut view = cube.clone();
+        view.apply_alg(&parse_alg(rotation)?);
+        if let Some(found) = cross(&view, best.unwrap_or(limit)) {
+            best = Some(found.len());
+        }
+    }
+    best
+}
+
+fn xcross(view: &Cube, limit: usize) -> Option<XCross> {
+    let goal = homes();
+    let mut best: Option<XCross> = None;
+    for (turns, &slot) in XCROSS_SLOTS.iter().enumerate() {
+        let depth = match &best {
+            Some(b) if b.moves.is_empty() => break,
+            Some(b) => b.moves.len() - 1,
+            None => limit
+        };
+        let turn = [Move::new(Y, turns as u8)];
+        let mut turned = view.clone();
+        turned.apply_alg(&turn);
+        let start = stickers(&turned)?;
+        let found = Search {
+            moves: &moves(),
+            tables: tables(),
+            goal: &|s| s == goal
+        }.first(&start, depth);
+        if let Some(moves) = found {
+            best = Some(XCross { slot, moves: unrotate(&moves, &turn) });
+        }
+    }
+    best
+}
+
+pub fn analyse(cube: &Cube) -> Option<Vec<CrossAnalysis>> {
+    let mut result = Vec::new();
+    for rotation in BOTTOM_ROTATIONS {
+        let rotation = parse_alg(rotation)?;
+        let mut view = cube.clone();
+        view.apply_alg(&rotation);
+        let cross = cross(&view, 10)?;
+        let xcross = xcross(&view, cross.len() + XCROSS_MARGIN);
+        result.push(CrossAnalysis {
+            bottom: view.centre_colors()[5],
+            rotation,
+            cross,
+            xcross
+        });
+    }
+    Some(result)
+}
+
+pub fn analyse_scramble(scramble: &str) -> Option<Vec<CrossAnalysis>> {
+    analyse(&Cube::from(scramble))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+
+    fn solved_pieces(view: &Cube, edges: &[usize], corners: &[usize]) -> bool {
+        let scheme = view.centre_colors();
+        edges.iter().all(|&e| view.edge_sticker_in(e, &scheme) == Some(edge_home(e)))
+            && corners.iter().all(|&c| view.corner_sticker_in(c, &scheme) == Some(corner_home(c)))
+    }
+
+    #[test]
+    fn check_analyse() {
+        let analysis = analyse_scramble(SCRAMBLE).unwrap();
+        let bottoms: Vec<Stiker> = analysis.iter().map(|a| a.bottom).collect();
+        assert_eq!(bottoms, [Stiker::Y, Stiker::O, Stiker::B, Stiker::R, Stiker::G, Stiker::W]);
+        for colour in &analysis {
+            let mut view = Cube::from(SCRAMBLE);
+            view.apply_alg(&colour.rotation);
+            let mut crossed = view.clone();
+            crossed.apply_alg(&colour.cross);
+            assert!(solved_pieces(&crossed, &CROSS, &[]));
+            assert!(colour.cross.len() <= 8);
+            if let Some(xcross) = &colour.xcross {
+                let slot = XCROSS_SLOTS.iter().position(|&s| s == xcross.slot).unwrap();
+                view.apply_alg(&xcross.moves);
+                assert!(solved_pieces(&view, &CROSS, &[]));
+                assert!(solved_pieces(&view, &[[8, 11, 10, 9][slot]], &[[4, 7, 6, 5][slot]]));
+                assert!(xcross.moves.len() <= colour.cross.len() + XCROSS_MARGIN);
+            }
+        }
+    }
+
+    #[test]
+    fn check_short_cross() {
+        let analysis = analyse_scramble("R U R' F2 D").unwrap();
+        let white = &analysis[5];
+        assert_eq!(white.cross, parse_alg("D' F2").unwrap());
+        let xcross = white.xcross.as_ref().unwrap();
+        assert_eq!((xcross.slot, xcross.moves.len()), ("BR", 2));
+        assert!(analyse(&Cube::default()).unwrap().iter().all(|a| a.cross.is_empty()));
+        assert_eq!(shortest_cross(&Cube::from("R U R' F2 D"), 8), Some(2));
+        assert_eq!(shortest_cross(&Cube::from(SCRAMBLE), 2), None);
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::facelet::*;
+use crate::notation::{Layer::*, Move, LAYERS};
+use crate::search::is_redundant;
+use crate::tables::{Table, cached};
+
+// Pieces and their twists by slot, in the order of CORNER_NAMES and
+// EDGE_NAMES. A corner twist tells which of the slot facelets holds the
+// U/D sticker, an edge is flipped when its reference sticker is off the
+// reference facelet of the slot.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct CubieCube {
+    pub cp: [u8; 8],
+    pub co: [u8; 8],
+    pub ep: [u8; 12],
+    pub eo: [u8; 12]
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        CubieCube {
+            cp: [0, 1, 2, 3, 4, 5, 6, 7],
+            co: [0; 8],
+            ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            eo: [0; 12]
+        }
+    }
+}
+
+fn find<const N: usize>(colors: [Stiker; N], pieces: &[[Stiker; N]]) -> Option<(u8, u8)> {
+    for (piece, piece_colors) in pieces.iter().enumerate() {
+        for twist in 0..N {
+            if (0..N).all(|i| colors[(i + twist) % N] == piece_colors[i]) {
+                return Some((piece as u8, twist as u8));
+            }
+        }
+    }
+    None
+}
+
+impl CubieCube {
+    // Reads the pieces against the cube's own centres, None if some
+    // sticker group is not a piece
+    pub fn from_cube(cube: &Cube) -> Option<Self> {
+        let scheme = cube.centre_colors();
+        let corners = CORNER_FACELETS.map(|c| c.map(|f| scheme[face_of(f)]));
+        let edges = EDGE_FACELETS.map(|e| e.map(|f| scheme[face_of(f)]));
+        let mut cubie = CubieCube::default();
+        for (slot, facelets) in CORNER_FACELETS.iter().enumerate() {
+            let (piece, twist) = find(facelets.map(|f| cube.color(f)), &corners)?;
+            cubie.cp[slot] = piece;
+            cubie.co[slot] = twist;
+        }
+        for (slot, facelets) in EDGE_FACELETS.iter().enumerate() {
+            let (piece, flip) = find(facelets.map(|f| cube.color(f)), &edges)?;
+            cubie.ep[slot] = piece;
+            cubie.eo[slot] = flip;
+        }
+        Some(cubie)
+    }
+    pub fn to_cube(&self) -> Cube {
+        let solved = Cube::default();
+        let mut cube = Cube::default();
+        for slot in 0..8 {
+            let piece = CORNER_FACELETS[self.cp[slot] as usize];
+            for (i, &f) in piece.iter().enumerate() {
+                let (row, col) = CORNER_FACELETS[slot][(i + self.co[slot] as usize) % 3];
+                cube.scan[row][col] = solved.color(f);
+            }
+        }
+        for slot in 0..12 {
+            let piece = EDGE_FACELETS[self.ep[slot] as usize];
+            for (i, &f) in piece.iter().enumerate() {
+                let (row, col) = EDGE_FACELETS[slot][(i + self.eo[slot] as usize) % 2];
+                cube.scan[row][col] = solved.color(f);
+            }
+        }
+        cube
+    }
+    // self followed by other
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut result = CubieCube::default();
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+    pub fn inverse(&self) -> CubieCube {
+        let mut result = CubieCube::default();
+        for i in 0..8 {
+            let piece = self.cp[i] as usize;
+            result.cp[piece] = i as u8;
+            result.co[piece] = (3 - self.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let piece = self.ep[i] as usize;
+            result.ep[piece] = i as u8;
+            result.eo[piece] = self.eo[i];
+        }
+        result
+    }
+    // Slice moves and rotations count against the centres, so M is R L'
+    // and x does nothing
+    pub fn of_move(m: Move) -> &'static CubieCube {
+        static MOVES: OnceLock<HashMap<Move, CubieCube>> = OnceLock::new();
+        let moves = MOVES.get_or_init(|| {
+            LAYERS.iter()
+                .flat_map(|&layer| (0..4).map(move |amount| Move::new(layer, amount)))
+                .map(|m| {
+                    let mut cube = Cube::default();
+                    cube.apply(m);
+                    (m, CubieCube::from_cube(&cube).unwrap())
+                })
+                .collect()
+        });
+        &moves[&m]
+    }
+    pub fn apply(&mut self, m: Move) {
+        *self = self.multiply(CubieCube::of_move(m));
+    }
+    pub fn apply_alg(&mut self, alg: &[Move]) {
+        for &m in alg {
+            self.apply(m);
+        }
+    }
+    pub fn corner_parity(&self) -> u8 {
+        parity(&self.cp)
+    }
+    pub fn edge_parity(&self) -> u8 {
+        parity(&self.ep)
+    }
+    pub fn is_solvable(&self) -> bool {
+        let mut corners = self.cp;
+        corners.sort();
+        let mut edges = self.ep;
+        edges.sort();
+        corners == CubieCube::default().cp
+            && edges == CubieCube::default().ep
+            && self.co.iter().map(|&c| c as u32).sum::<u32>() % 3 == 0
+            && self.eo.iter().map(|&e| e as u32).sum::<u32>() % 2 == 0
+            && self.corner_parity() == self.edge_parity()
+    }
+}
+
+pub(crate) fn parity(perm: &[u8]) -> u8 {
+    let mut parity = 0;
+    for i in 0..perm.len() {
+        for j in i + 1..perm.len() {
+            if perm[i] > perm[j] {
+                parity ^= 1;
+            }
+        }
+    }
+    parity
+}
+
+// Breadth first distances of whatever `project` keeps of the cube, it
+// has to be enough to tell how the moves change it
+pub(crate) fn distances<K: Hash + Eq>(
+    starts: &[CubieCube],
+    moves: &[Move],
+    project: impl Fn(&CubieCube) -> K
+) -> HashMap<K, u8> {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    for start in starts {
+        if distances.insert(project(start), 0).is_none() {
+            queue.push_back((*start, 0));
+        }
+    }
+    while let Some((cube, distance)) = queue.pop_front() {
+        for &m in moves {
+            let next = cube.multiply(CubieCube::of_move(m));
+            distances.entry(project(&next)).or_insert_with(|| {
+                queue.push_back((next, distance + 1));
+                distance + 1
+            });
+        }
+    }
+    distances
+}
+
+// Iterative deepening on the whole cube, `bound` has to be admissible
+pub(crate) fn search(
+    start: &CubieCube,
+    moves: &[Move],
+    bound: &dyn Fn(&CubieCube) -> u8,
+    goal: &dyn Fn(&CubieCube) -> bool,
+    max_depth: usize
+) -> Option<Vec<Move>> {
+    fn step(
+        cube: &CubieCube,
+        depth: usize,
+        path: &mut Vec<Move>,
+        moves: &[Move],
+        bound: &dyn Fn(&CubieCube) -> u8,
+        goal: &dyn Fn(&CubieCube) -> bool
+    ) -> bool {
+        if depth == 0 {
+            return goal(cube);
+        }
+        if bound(cube) as usize > depth {
+            return false;
+        }
+        for &m in moves {
+            if is_redundant(path.last().copied(), m) {
+                continue;
+            }
+            path.push(m);
+            if step(&cube.multiply(CubieCube::of_move(m)), depth - 1, path, moves, bound, goal) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+    let mut path = Vec::new();
+    (0..=max_depth).find(|&depth| step(start, depth, &mut path, moves, bound, goal))?;
+    Some(path)
+}
+
+// Where four of the pieces are and how they are turned
+fn group_key<const N: usize>(perm: &[u8; N], twists: &[u8; N], pieces: [u8; 4]) -> [u8; 8] {
+    let mut key = [0; 8];
+    for (slot, &piece) in perm.iter().enumerate() {
+        if let Some(i) = pieces.iter().position(|&p| p == piece) {
+            key[2 * i] = slot as u8;
+            key[2 * i + 1] = twists[slot];
+        }
+    }
+    key
+}
+
+const CORNER_GROUPS: [[u8; 4]; 2] = [[0, 1, 2, 3], [4, 5, 6, 7]];
+const EDGE_GROUPS: [[u8; 4]; 3] = [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]];
+
+// Face turns to solve the groups of pieces, each on its own
+struct Bounds {
+    corners: [HashMap<[u8; 8], u8>; 2],
+    edges: [HashMap<[u8; 8], u8>; 3]
+}
+
+impl Table for Bounds {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.corners.write(out);
+        self.edges.write(out);
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        Some(Bounds { corners: Table::read(input)?, edges: Table::read(input)? })
+    }
+}
+
+fn bounds() -> &'static Bounds {
+    static BOUNDS: OnceLock<Bounds> = OnceLock::new();
+    BOUNDS.get_or_init(|| cached("distance_bound", || {
+        let solved = [CubieCube::default()];
+        let moves = Move::all(&[U, D, L, R, F, B]);
+        Bounds {
+            corners: CORNER_GROUPS.map(|group| distances(&solved, &moves, |c| group_key(&c.cp, &c.co, group))),
+            edges: EDGE_GROUPS.map(|group| distances(&solved, &moves, |c| group_key(&c.ep, &c.eo, group)))
+        }
+    }))
+}
+
+// Face turns the cube takes at least, the most that any group of pieces
+// takes by itself
+pub(crate) fn distance_bound(c: &CubieCube) -> u8 {
+    let b = bounds();
+    let get = |distance: Option<&u8>| distance.copied().unwrap_or(u8::MAX);
+    let corners = CORNER_GROUPS.iter().zip(&b.corners).map(|(&group, table)| get(table.get(&group_key(&c.cp, &c.co, group))));
+    let edges = EDGE_GROUPS.iter().zip(&b.edges).map(|(&group, table)| get(table.get(&group_key(&c.ep, &c.eo, group))));
+    corners.chain(edges).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+
+    #[test]
+    fn check_from_cube() {
+        let alg = parse_alg(SCRAMBLE).unwrap();
+        let mut cube = Cube::default();
+        cube.apply_alg(&alg);
+        let mut cubie = CubieCube::default();
+        cubie.apply_alg(&alg);
+        assert_eq!(CubieCube::from_cube(&cube), Some(cubie));
+        assert_eq!(cubie.to_cube(), cube);
+        assert!(cubie.is_solvable());
+        assert_eq!(cubie.multiply(&cubie.inverse()), CubieCube::default());
+    }
+
+    #[test]
+    fn check_from_scan() {
+        let cube = Cube::from_scan(crate::test::SCRAMBLED_SCAN);
+        assert_eq!(CubieCube::from_cube(&cube).unwrap().to_cube(), cube);
+        let mut broken = cube.clone();
+        broken.scan[0][3] = Stiker::W;
+        assert_eq!(CubieCube::from_cube(&broken), None);
+    }
+
+    #[test]
+    fn check_twists() {
+        let mut cubie = CubieCube::default();
+        cubie.apply(Move::new(crate::notation::Layer::F, 1));
+        assert_eq!(cubie.eo, [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0]);
+        assert_eq!(cubie.co, [1, 2, 0, 0, 2, 1, 0, 0]);
+        assert_eq!(cubie.corner_parity(), 1);
+    }
+
+    #[test]
+    fn check_distance_bound() {
+        let bound = |alg: &str| {
+            let mut cubie = CubieCube::default();
+            cubie.apply_alg(&parse_alg(alg).unwrap());
+            distance_bound(&cubie)
+        };
+        assert_eq!(bound(""), 0);
+        assert_eq!(bound("R"), 1);
+        assert_eq!(bound("R U R' U'"), 4);
+        assert!((1..=7).contains(&bound("R U R' U R U2 R'")));
+        assert!(bound(SCRAMBLE) <= 20);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::cross::{self, CROSS, XCROSS_SLOTS};
+use crate::facelet::*;
+use crate::notation::{Layer::*, Move, invert, parse_alg};
+use crate::search::Search;
+
+const PAIR_EDGE: usize = 8;                 // FR
+const PAIR_CORNER: usize = 4;               // DFR
+
+// The FR slot, that the stickers of the first two layers may be off in
+const SLOT: [Facelet; 5] = [(4, 5), (4, 6), (5, 5), (5, 6), (6, 5)];
+
+// Everything of the first two layers but the FR pair is solved
+pub fn is_last_slot(cube: &Cube) -> bool {
+    let colors = cube.centre_colors();
+    (4..9).all(|row| (0..12).all(|col| {
+        let facelet = (row, col);
+        SLOT.contains(&facelet) || (row >= 6 && !(3..6).contains(&col))
+            || cube.color(facelet) == colors[face_of(facelet)]
+    }))
+}
+
+fn pair(cube: &Cube) -> Option<(u8, u8)> {
+    let scheme = cube.centre_colors();
+    Some((cube.edge_sticker_in(PAIR_EDGE, &scheme)?, cube.corner_sticker_in(PAIR_CORNER, &scheme)?))
+}
+
+fn turned(pair: (u8, u8), amount: u8) -> (u8, u8) {
+    let mut stickers = [pair.0, pair.1];
+    apply_to_stickers(&mut stickers, Move::new(U, amount));
+    (stickers[0], stickers[1])
+}
+
+// The standard cases, by an algorithm each. The pieces are both in the
+// top layer for 1 to 24, the corner is in the slot for 25 to 30, the
+// edge for 31 to 36 and both of them for 37 to 41.
+const F2L_ALGS: [&str; 41] = [
+    "U R U' R'", "U' F' U F", "F' U' F", "R U R'",
+    "U' R U R' U2 R U' R'", "U F' U' F U2 F' U F", "U' R U2 R' U2 R U' R'", "U F' U2 F U2 F' U F",
+    "U' R U' R' U F' U' F", "U' R U R' U R U R'", "U' R U2 R' U F' U' F", "R U' R' U R U' R' U2 R U' R'",
+    "U F' U F U' F' U' F", "U' R U' R' U R U R'", "R' D' R U' R' D R U R U' R'", "R U' R' U2 F' U' F",
+    "R U2 R' U' R U R'", "F' U2 F U F' U' F", "U R U2 R' U R U' R'", "U' F' U2 F U' F' U F",
+    "U2 R U R' U R U' R'", "U2 F' U' F U' F' U F", "U R U' R' U' R U' R' U R U' R'",
+    "U' F' U F U F' U F U' F' U F",
+    "U' R' F R F' R U R'", "U R U' R' F R' F' R", "R U' R' U R U' R'", "F' U F U' F' U F",
+    "R U R' U' R U R'", "F' U' F U F' U' F",
+    "U' R' F R F' R U' R'", "U R U' R' U R U' R' U R U' R'", "U' R U' R' U2 R U' R'",
+    "U R U R' U2 R U R'", "U' R U R' U F' U' F", "U F' U' F U' R U R'",
+    "R2 U2 F R2 F' U2 R' U R'", "R U' R' U' R U R' U2 R U' R'", "R U' R' U R U2 R' U R U' R'",
+    "r U' r' U2 r U r' R U R'", "R U' R' F' L' U2 L F"
+];
+
+// Cases are the pair up to a turn of U, the solved pair is "skip"
+fn cases() -> &'static HashMap<(u8, u8), (String, u8)> {
+    static CASES: OnceLock<HashMap<(u8, u8), (String, u8)>> = OnceLock::new();
+    CASES.get_or_init(|| {
+        let mut cases = HashMap::new();
+        let named = F2L_ALGS.iter().enumerate().map(|(i, alg)| (format!("F2L {}", i + 1), *alg));
+        for (name, alg) in [("skip".to_string(), "")].into_iter().chain(named) {
+            let mut cube = Cube::default();
+            cube.apply_alg(&invert(&parse_alg(alg).unwrap()));
+            let stickers = pair(&cube).unwrap();
+            for auf in 0..4 {
+                cases.entry(turned(stickers, 4 - auf)).or_insert((name.clone(), auf));
+            }
+        }
+        cases
+    })
+}
+
+pub fn f2l_cases() -> Vec<String> {
+    let mut names: Vec<String> = cases().values().map(|(name, _)| name.clone()).collect();
+    names.sort_by_key(|name| name.trim_start_matches("F2L ").parse::<usize>().unwrap_or(0));
+    names.dedup();
+    names
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct F2lCase {
+    pub name: String,
+    // brings the pair to where the case is numbered from
+    pub auf: Move
+}
+
+// The case of the FR pair, None unless the rest of F2L is solved
+pub fn recognise_f2l(cube: &Cube) -> Option<F2lCase> {
+    if !is_last_slot(cube) {
+        return None;
+    }
+    let (name, auf) = cases().get(&pair(cube)?)?;
+    Some(F2lCase { name: name.clone(), auf: Move::new(U, *auf) })
+}
+
+// The slots in the order of XCROSS_SLOTS, y turns bring each to FR
+const SLOT_EDGES: [usize; 4] = [8, 11, 10, 9];
+const SLOT_CORNERS: [usize; 4] = [4, 7, 6, 5];
+
+// Insertions are looked for up to this many moves
+pub const INSERTION_LIMIT: usize = 11;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairState {
+    pub slot: &'static str,
+    // the slots of the pieces, as in CORNER_NAMES and EDGE_NAMES
+    pub corner: &'static str,
+    pub corner_twist: u8,
+    pub edge: &'static str,
+    pub edge_flipped: bool,
+    // the corner and the edge are joined like in the solved slot
+    pub paired: bool,
+    pub solved: bool,
+    // the shortest moves, that solve the pair and keep the cross and
+    // the solved pairs, None if there are none up to INSERTION_LIMIT
+    pub insertion: Option<Vec<Move>>
+}
+
+#[derive(Debug, Clone)]
+pub struct PairAnalysis {
+    pub pairs: Vec<PairState>,
+    // the slots, that no solved pair takes
+    pub free_slots: Vec<&'static str>
+}
+
+fn is_paired(cube: &Cube, slot: usize) -> bool {
+    let scheme = cube.centre_colors();
+    let facelets: Vec<Facelet> = CORNER_FACELETS[SLOT_CORNERS[slot]].iter()
+        .chain(&EDGE_FACELETS[SLOT_EDGES[slot]])
+        .copied()
+        .collect();
+    rotation_perms().iter().any(|perm| {
+        facelets.iter().all(|&f| cube.color(facelet(perm[sticker(f) as usize])) == scheme[face_of(f)])
+    })
+}
+
+fn is_solved_slot(cube: &Cube, slot: usize) -> bool {
+    let scheme = cube.centre_colors();
+    cube.edge_sticker_in(SLOT_EDGES[slot], &scheme) == Some(edge_home(SLOT_EDGES[slot]))
+        && cube.corner_sticker_in(SLOT_CORNERS[slot], &scheme) == Some(corner_home(SLOT_CORNERS[slot]))
+}
+
+// Seen from y turns that bring the slot to FR, the cross edges, the pair,
+// then the pieces of the solved slots
+fn insertion(cube: &Cube, slot: usize) -> Option<Vec<Move>> {
+    let turn = [Move::new(Y, slot as u8)];
+    let mut view = cube.clone();
+    view.apply_alg(&turn);
+    let scheme = view.centre_colors();
+    let mut stickers = Vec::new();
+    let mut goal = cross::homes();
+    for &edge in CROSS.iter().chain(&[SLOT_EDGES[0]]) {
+        stickers.push(view.edge_sticker_in(edge, &scheme)?);
+    }
+    stickers.push(view.corner_sticker_in(SLOT_CORNERS[0], &scheme)?);
+    for other in (1..4).filter(|&other| is_solved_slot(&view, other)) {
+        stickers.extend([edge_home(SLOT_EDGES[other]), corner_home(SLOT_CORNERS[other])]);
+        goal.extend([edge_home(SLOT_EDGES[other]), corner_home(SLOT_CORNERS[other])]);
+    }
+    let moves = Search {
+        moves: &cross::moves(),
+        tables: cross::tables(),
+        goal: &|s| s == goal
+    }.first(&stickers, INSERTION_LIMIT)?;
+    Some(unrotate(&moves, &turn))
+}
+
+// The four pairs around the cross on D, None unless the cross is solved
+pub fn analyse_pairs(cube: &Cube) -> Option<PairAnalysis> {
+    let scheme = cube.centre_colors();
+    if CROSS.iter().any(|&e| cube.edge_sticker_in(e, &scheme) != Some(edge_home(e))) {
+        return None;
+    }
+    let mut pairs = Vec::new();
+    for (slot, &name) in XCROSS_SLOTS.iter().enumerate() {
+        let corner = cube.corner_sticker_in(SLOT_CORNERS[slot], &scheme)?;
+        let (corner_at, corner_twist) = CORNER_FACELETS.iter().enumerate()
+            .find_map(|(at, f)| f.iter().position(|&f| sticker(f) == corner).map(|twist| (at, twist)))?;
+        let edge = cube.edge_sticker_in(SLOT_EDGES[slot], &scheme)?;
+        let (edge_at, edge_flip) = EDGE_FACELETS.iter().enumerate()
+            .find_map(|(at, f)| f.iter().position(|&f| sticker(f) == edge).map(|flip| (at, flip)))?;
+        let solved = is_solved_slot(cube, slot);
+        pairs.push(PairState {
+            slot: name,
+            corner: CORNER_NAMES[corner_at],
+            corner_twist: corner_twist as u8,
+            edge: EDGE_NAMES[edge_at],
+            edge_flipped: edge_flip == 1,
+            paired: is_paired(cube, slot),
+            solved,
+            insertion: if solved { Some(vec![]) } else { insertion(cube, slot) }
+        });
+    }
+    let free_slots = pairs.iter().filter(|p| !p.solved).map(|p| p.slot).collect();
+    Some(PairAnalysis { pairs, free_slots })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn case(setup: &str) -> Cube {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(setup).unwrap());
+        cube
+    }
+
+    #[test]
+    fn check_recognise_f2l() {
+        assert_eq!(f2l_cases().len(), 42);
+        assert_eq!(recognise_f2l(&Cube::default()).unwrap().name, "skip");
+        assert_eq!(recognise_f2l(&case("R U R' U'")).unwrap().name, "F2L 1");
+        assert_eq!(recognise_f2l(&case("R U' R'")).unwrap().name, "F2L 4");
+        assert_eq!(recognise_f2l(&case("F' L' U2 L F R U R'")).unwrap().name, "F2L 41");
+        let found = recognise_f2l(&case("R U R' U'")).unwrap();
+        let turned = recognise_f2l(&case("R U R' U")).unwrap();
+        assert_eq!(found.name, turned.name);
+        let mut cube = case("R U R' U");
+        cube.apply(turned.auf);
+        assert_eq!(recognise_f2l(&cube).unwrap().auf, Move::new(U, 0));
+        assert_ne!(found.name, recognise_f2l(&case("R U' R'")).unwrap().name);
+        assert_eq!(recognise_f2l(&case("L U L'")), None);
+    }
+
+    #[test]
+    fn check_analyse_pairs() {
+        let cube = case("R U R' L' U' L");
+        let analysis = analyse_pairs(&cube).unwrap();
+        assert_eq!(analysis.free_slots, ["FR", "FL"]);
+        let fl = &analysis.pairs[3];
+        assert_eq!((fl.slot, fl.corner, fl.edge), ("FL", "URF", "UF"));
+        assert!(fl.paired && !fl.solved);
+        assert_eq!(fl.insertion.as_ref().unwrap().len(), 3);
+        assert!(analysis.pairs[1].solved && analysis.pairs[1].insertion == Some(vec![]));
+        for (slot, pair) in analysis.pairs.iter().enumerate() {
+            let mut solved = cube.clone();
+            solved.apply_alg(pair.insertion.as_ref().unwrap());
+            assert!(is_solved_slot(&solved, slot));
+            assert!(analyse_pairs(&solved).is_some());
+            assert!((0..4).all(|other| !analysis.pairs[other].solved || is_solved_slot(&solved, other)));
+        }
+        let fr = analyse_pairs(&case("R U2 R' U R U' R'")).unwrap().pairs[0].clone();
+        assert_eq!((fr.corner, fr.corner_twist, fr.edge, fr.edge_flipped), ("UBR", 2, "UL", false));
+        assert!(!fr.paired && fr.insertion.unwrap().len() <= 7);
+        assert!(analyse_pairs(&case("F")).is_none());
+    }
+}
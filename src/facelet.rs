@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::notation::{Layer::{X, Y, Z}, Move, LAYERS};
+
+pub type Facelet = (usize, usize);
+
+// U, L, F, R, B, D
+pub const CENTER_FACELETS: [Facelet; 6] = [
+    (1, 4), (4, 1), (4, 4), (4, 7), (4, 10), (7, 4)
+];
+
+pub const EDGE_NAMES: [&str; 12] = [
+    "UR", "UF", "UL", "UB", "DR", "DF", "DL", "DB", "FR", "FL", "BL", "BR"
+];
+
+// the first facelet of an edge is its U/D sticker, or F/B for the E slice
+pub const EDGE_FACELETS: [[Facelet; 2]; 12] = [
+    [(1, 5), (3, 7)],
+    [(2, 4), (3, 4)],
+    [(1, 3), (3, 1)],
+    [(0, 4), (3, 10)],
+    [(7, 5), (5, 7)],
+    [(6, 4), (5, 4)],
+    [(7, 3), (5, 1)],
+    [(8, 4), (5, 10)],
+    [(4, 5), (4, 6)],
+    [(4, 3), (4, 2)],
+    [(4, 11), (4, 0)],
+    [(4, 9), (4, 8)]
+];
+
+pub const CORNER_NAMES: [&str; 8] = [
+    "URF", "UFL", "ULB", "UBR", "DFR", "DLF", "DBL", "DRB"
+];
+
+// facelets go clockwise, starting from the U/D sticker
+pub const CORNER_FACELETS: [[Facelet; 3]; 8] = [
+    [(2, 5), (3, 6), (3, 5)],
+    [(2, 3), (3, 3), (3, 2)],
+    [(0, 3), (3, 0), (3, 11)],
+    [(0, 5), (3, 9), (3, 8)],
+    [(6, 5), (5, 5), (5, 6)],
+    [(6, 3), (5, 2), (5, 3)],
+    [(8, 3), (5, 11), (5, 0)],
+    [(8, 5), (5, 8), (5, 9)]
+];
+
+// A sticker is a facelet packed as row * 12 + column, this is how
+// the searches track pieces around the net
+pub(crate) const STICKERS: usize = 108;
+
+pub(crate) fn sticker(facelet: Facelet) -> u8 {
+    (facelet.0 * 12 + facelet.1) as u8
+}
+
+pub(crate) fn facelet(sticker: u8) -> Facelet {
+    (sticker as usize / 12, sticker as usize % 12)
+}
+
+pub(crate) fn edge_home(piece: usize) -> u8 {
+    sticker(EDGE_FACELETS[piece][0])
+}
+
+pub(crate) fn corner_home(piece: usize) -> u8 {
+    sticker(CORNER_FACELETS[piece][0])
+}
+
+pub(crate) fn center_home(center: usize) -> u8 {
+    sticker(CENTER_FACELETS[center])
+}
+
+// Where each sticker goes after the move, found by following a single
+// sticker on an otherwise void cube through the rotate functions
+pub(crate) fn move_perm(m: Move) -> &'static [u8; STICKERS] {
+    static PERMS: OnceLock<HashMap<Move, [u8; STICKERS]>> = OnceLock::new();
+    let perms = PERMS.get_or_init(|| {
+        let mut perms = HashMap::new();
+        for m in LAYERS.iter().flat_map(|&layer| (0..4).map(move |amount| Move::new(layer, amount))) {
+            let mut perm = [0; STICKERS];
+            for (i, target) in perm.iter_mut().enumerate() {
+                let mut cube = Cube { scan: [[Stiker::V; 12]; 9] };
+                let (row, col) = facelet(i as u8);
+                cube.scan[row][col] = Stiker::B;
+                cube.apply(m);
+                *target = i as u8;
+                for (row, layer) in cube.scan.iter().enumerate() {
+                    for (col, &letter) in layer.iter().enumerate() {
+                        if letter == Stiker::B {
+                            *target = sticker((row, col));
+                        }
+                    }
+                }
+            }
+            perms.insert(m, perm);
+        }
+        perms
+    });
+    &perms[&m]
+}
+
+pub(crate) fn apply_to_stickers(stickers: &mut [u8], m: Move) {
+    let perm = move_perm(m);
+    for s in stickers.iter_mut() {
+        *s = perm[*s as usize];
+    }
+}
+
+// 0 - U, 1 - L, 2 - F, 3 - R, 4 - B, 5 - D, like CENTER_FACELETS
+pub(crate) fn face_of(facelet: Facelet) -> usize {
+    match facelet {
+        (0..=2, _) => 0,
+        (6.., _) => 5,
+        (_, col) => 1 + col / 3
+    }
+}
+
+fn perm_of(alg: &[Move]) -> Vec<u8> {
+    let mut stickers: Vec<u8> = (0..STICKERS as u8).collect();
+    for &m in alg {
+        apply_to_stickers(&mut stickers, m);
+    }
+    stickers
+}
+
+// The 24 ways to hold the cube, as where each sticker goes
+pub(crate) fn rotation_perms() -> &'static [Vec<u8>] {
+    static PERMS: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+    PERMS.get_or_init(|| {
+        let firsts = [Move::new(X, 0), Move::new(X, 1), Move::new(X, 2), Move::new(X, 3), Move::new(Z, 1), Move::new(Z, 3)];
+        firsts.iter()
+            .flat_map(|&first| (0..4).map(move |turns| perm_of(&[first, Move::new(Y, turns)])))
+            .collect()
+    })
+}
+
+// The moves that do the same to the cube as `alg` does after `rotation`,
+// but seen from before the rotation
+pub(crate) fn unrotate(alg: &[Move], rotation: &[Move]) -> Vec<Move> {
+    let undo = crate::notation::invert(rotation);
+    alg.iter().map(|&m| {
+        let target = perm_of(&[rotation, &[m], &undo].concat());
+        LAYERS.iter()
+            .flat_map(|&layer| (1..4).map(move |amount| Move::new(layer, amount)))
+            .find(|&candidate| perm_of(&[candidate]) == target)
+            .unwrap_or(m)
+    }).collect()
+}
+
+impl Cube {
+    pub fn color(&self, facelet: Facelet) -> Stiker {
+        self.scan[facelet.0][facelet.1]
+    }
+    pub(crate) fn centre_colors(&self) -> [Stiker; 6] {
+        CENTER_FACELETS.map(|f| self.color(f))
+    }
+    fn find_piece(&self, slots: &[&[Facelet]], colors: &[Stiker]) -> Option<u8> {
+        let size = colors.len();
+        for slot in slots {
+            for twist in 0..size {
+                if (0..size).all(|i| self.color(slot[(i + twist) % size]) == colors[i]) {
+                    return Some(sticker(slot[twist]));
+                }
+            }
+        }
+        None
+    }
+    // the sticker, where the reference sticker of the solved edge now is
+    pub(crate) fn edge_sticker(&self, piece: usize) -> Option<u8> {
+        self.edge_sticker_in(piece, &Cube::default().centre_colors())
+    }
+    pub(crate) fn corner_sticker(&self, piece: usize) -> Option<u8> {
+        self.corner_sticker_in(piece, &Cube::default().centre_colors())
+    }
+    // same, with the piece colours taken from the given centre colours
+    pub(crate) fn edge_sticker_in(&self, piece: usize, scheme: &[Stiker; 6]) -> Option<u8> {
+        let colors = EDGE_FACELETS[piece].map(|f| scheme[face_of(f)]);
+        let slots: Vec<&[Facelet]> = EDGE_FACELETS.iter().map(|s| &s[..]).collect();
+        self.find_piece(&slots, &colors)
+    }
+    pub(crate) fn corner_sticker_in(&self, piece: usize, scheme: &[Stiker; 6]) -> Option<u8> {
+        let colors = CORNER_FACELETS[piece].map(|f| scheme[face_of(f)]);
+        let slots: Vec<&[Facelet]> = CORNER_FACELETS.iter().map(|s| &s[..]).collect();
+        self.find_piece(&slots, &colors)
+    }
+    pub(crate) fn center_sticker(&self, center: usize) -> Option<u8> {
+        let color = Cube::default().color(CENTER_FACELETS[center]);
+        CENTER_FACELETS.iter()
+            .find(|&&f| self.color(f) == color)
+            .map(|&f| sticker(f))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F M E' S2 r x y' z2 u";
+
+    #[test]
+    fn check_move_perm() {
+        let alg = parse_alg(SCRAMBLE).unwrap();
+        let solved = Cube::default();
+        let mut cube = Cube::default();
+        cube.apply_alg(&alg);
+        let mut stickers: Vec<u8> = (0..STICKERS as u8).collect();
+        for &m in &alg {
+            apply_to_stickers(&mut stickers, m);
+        }
+        for (i, &s) in stickers.iter().enumerate() {
+            assert!(cube.color(facelet(s)) == solved.color(facelet(i as u8)));
+        }
+    }
+
+    #[test]
+    fn check_pieces() {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(SCRAMBLE).unwrap());
+        let mut stickers: Vec<u8> = (0..12).map(|p| cube.edge_sticker(p).unwrap()).collect();
+        stickers.extend((0..8).map(|p| cube.corner_sticker(p).unwrap()));
+        stickers.extend((0..6).map(|c| cube.center_sticker(c).unwrap()));
+        stickers.sort();
+        stickers.dedup();
+        assert_eq!(stickers.len(), 26);
+        assert_eq!(Cube::default().edge_sticker(4), Some(edge_home(4)));
+        assert_eq!(Cube::default().corner_sticker(7), Some(corner_home(7)));
+    }
+
+    #[test]
+    fn check_unrotate() {
+        let rotation = parse_alg("x y").unwrap();
+        let alg = parse_alg("R U' M2 r").unwrap();
+        let mut rotated = Cube::default();
+        rotated.apply_alg(&rotation);
+        rotated.apply_alg(&alg);
+        let mut cube = Cube::default();
+        cube.apply_alg(&unrotate(&alg, &rotation));
+        cube.apply_alg(&rotation);
+        assert_eq!(cube, rotated);
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+// Reports are written out as JSON by hand, the crate keeps to one
+// dependency. Fields come out in the order they are given.
+
+pub trait ToJson {
+    fn write_json(&self, out: &mut String);
+
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+macro_rules! number_json {
+    ($($t:ty),*) => {$(
+        impl ToJson for $t {
+            fn write_json(&self, out: &mut String) {
+                write!(out, "{}", self).unwrap();
+            }
+        }
+    )*}
+}
+
+number_json!(u8, u16, u32, u64, usize, i32, i64);
+
+impl ToJson for f64 {
+    fn write_json(&self, out: &mut String) {
+        if self.is_finite() {
+            write!(out, "{}", self).unwrap();
+        } else {
+            out.push_str("null");
+        }
+    }
+}
+
+impl ToJson for bool {
+    fn write_json(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl ToJson for str {
+    fn write_json(&self, out: &mut String) {
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+                c => out.push(c)
+            }
+        }
+        out.push('"');
+    }
+}
+
+impl ToJson for String {
+    fn write_json(&self, out: &mut String) {
+        self.as_str().write_json(out);
+    }
+}
+
+impl ToJson for char {
+    fn write_json(&self, out: &mut String) {
+        self.to_string().write_json(out);
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn write_json(&self, out: &mut String) {
+        (**self).write_json(out);
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_json(out),
+            None => out.push_str("null")
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn write_json(&self, out: &mut String) {
+        out.push('[');
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            item.write_json(out);
+        }
+        out.push(']');
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn write_json(&self, out: &mut String) {
+        self.as_slice().write_json(out);
+    }
+}
+
+pub(crate) fn write_object(out: &mut String, fields: &[(&str, &dyn ToJson)]) {
+    out.push('{');
+    for (i, (name, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        name.write_json(out);
+        out.push(':');
+        value.write_json(out);
+    }
+    out.push('}');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Point {
+        name: String,
+        at: Vec<usize>,
+        seen: Option<bool>
+    }
+
+    impl ToJson for Point {
+        fn write_json(&self, out: &mut String) {
+            write_object(out, &[("name", &self.name), ("at", &self.at), ("seen", &self.seen)]);
+        }
+    }
+
+    #[test]
+    fn check_json() {
+        let point = Point { name: "say \"hi\"\n\\".to_string(), at: vec![1, 2], seen: None };
+        assert_eq!(point.to_json(), r#"{"name":"say \"hi\"\n\\","at":[1,2],"seen":null}"#);
+        assert_eq!(vec![Some(1.5), None].to_json(), "[1.5,null]");
+        assert_eq!("\u{1}".to_json(), r#""\u0001""#);
+    }
+}
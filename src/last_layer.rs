@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::facelet::*;
+use crate::notation::{Layer::*, Move, invert, parse_alg};
+
+// An algorithm for every case, in the order of the case numbers
+pub const OLL_ALGS: [&str; 57] = [
+    "R U2 R2 F R F' U2 R' F R F'",
+    "F R U R' U' F' f R U R' U' f'",
+    "f R U R' U' f' U' F R U R' U' F'",
+    "f R U R' U' f' U F R U R' U' F'",
+    "r' U2 R U R' U r",
+    "r U2 R' U' R U' r'",
+    "r U R' U R U2 r'",
+    "l' U' L U' L' U2 l",
+    "R U R' U' R' F R2 U R' U' F'",
+    "R U R' U R' F R F' R U2 R'",
+    "r U R' U R' F R F' R U2 r'",
+    "M' R' U' R U' R' U2 R U' R r'",
+    "F U R U' R2 F' R U R U' R'",
+    "R' F R U R' F' R F U' F'",
+    "r' U' r R' U' R U r' U r",
+    "r U r' R U R' U' r U' r'",
+    "R U R' U R' F R F' U2 R' F R F'",
+    "r U R' U R U2 r2 U' R U' R' U2 r",
+    "M U R U R' U' M' R' F R F'",
+    "r U R' U' M2 U R U' R' U' M'",
+    "R U2 R' U' R U R' U' R U' R'",
+    "R U2 R2 U' R2 U' R2 U2 R",
+    "R2 D' R U2 R' D R U2 R",
+    "r U R' U' r' F R F'",
+    "F' r U R' U' r' F R",
+    "R U2 R' U' R U' R'",
+    "R U R' U R U2 R'",
+    "r U R' U' r' R U R U' R'",
+    "R U R' U' R U' R' F' U' F R U R'",
+    "F R' F R2 U' R' U' R U R' F2",
+    "R' U' F U R U' R' F' R",
+    "L U F' U' L' U L F L'",
+    "R U R' U' R' F R F'",
+    "R U R2 U' R' F R U R U' F'",
+    "R U2 R2 F R F' R U2 R'",
+    "L' U' L U' L' U L U L F' L' F",
+    "F R' F' R U R U' R'",
+    "R U R' U R U' R' U' R' F R F'",
+    "L F' L' U' L U F U' L'",
+    "R' F R U R' U' F' U R",
+    "R U R' U R U2 R' F R U R' U' F'",
+    "R' U' R U' R' U2 R F R U R' U' F'",
+    "F' U' L' U L F",
+    "F U R U' R' F'",
+    "F R U R' U' F'",
+    "R' U' R' F R F' U R",
+    "R' U' R' F R F' R' F R F' U R",
+    "F R U R' U' R U R' U' F'",
+    "r U' r2 U r2 U r2 U' r",
+    "r' U r2 U' r2 U' r2 U r'",
+    "F U R U' R' U R U' R' F'",
+    "R U R' U R U' B U' B' R'",
+    "l' U2 L U L' U' L U L' U l",
+    "r U2 R' U' R U R' U' R U' r'",
+    "R' F R U R U' R2 F' R2 U' R' U R U R'",
+    "r' U' r U' R' U R U' R' U R r' U r",
+    "R U R' U' M' U R U' r'"
+];
+
+pub const PLL_ALGS: [(&str, &str); 21] = [
+    ("Aa", "x R' U R' D2 R U' R' D2 R2 x'"),
+    ("Ab", "x R2 D2 R U R' D2 R U' R x'"),
+    ("E", "x' R U' R' D R U R' D' R U R' D R U' R' D' x"),
+    ("F", "R' U' F' R U R' U' R' F R2 U' R' U' R U R' U R"),
+    ("Ga", "R2 U R' U R' U' R U' R2 U' D R' U R D'"),
+    ("Gb", "R' U' R U D' R2 U R' U R U' R U' R2 D"),
+    ("Gc", "R2 U' R U' R U R' U R2 U D' R U' R' D"),
+    ("Gd", "R U R' U' D R2 U' R U' R' U R' U R2 D'"),
+    ("H", "M2 U M2 U2 M2 U M2"),
+    ("Ja", "R' U L' U2 R U' R' U2 R L"),
+    ("Jb", "R U R' F' R U R' U' R' F R2 U' R'"),
+    ("Na", "R U R' U R U R' F' R U R' U' R' F R2 U' R' U2 R U' R'"),
+    ("Nb", "R' U R U' R' F' U' F R U R' F R' F' R U' R"),
+    ("Ra", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    ("Rb", "R2 F R U R U' R' F' R U2 R' U2 R"),
+    ("T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+    ("Ua", "M2 U M U2 M' U M2"),
+    ("Ub", "M2 U' M U2 M' U' M2"),
+    ("V", "R U' R U R' D R D' R U' D R2 U R2 D' R2"),
+    ("Y", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("Z", "M' U M2 U M2 U M' U2 M2")
+];
+
+// The top face, then the top rows of L, F, R and B
+pub(crate) const LAST_LAYER: [Facelet; 21] = [
+    (0, 3), (0, 4), (0, 5), (1, 3), (1, 4), (1, 5), (2, 3), (2, 4), (2, 5),
+    (3, 0), (3, 1), (3, 2), (3, 3), (3, 4), (3, 5),
+    (3, 6), (3, 7), (3, 8), (3, 9), (3, 10), (3, 11)
+];
+
+// Every sticker below the top layer matches its centre
+pub fn is_f2l_solved(cube: &Cube) -> bool {
+    let colors = cube.centre_colors();
+    (4..9).all(|row| (0..12).all(|col| {
+        let facelet = (row, col);
+        (row >= 6 && !(3..6).contains(&col)) || cube.color(facelet) == colors[face_of(facelet)]
+    }))
+}
+
+// The face of the centre with the colour of each sticker, None for colours
+// that are not on a centre
+pub(crate) fn faces(cube: &Cube, facelets: &[Facelet]) -> Option<Vec<u8>> {
+    let colors = cube.centre_colors();
+    facelets.iter()
+        .map(|&f| colors.iter().position(|&c| c == cube.color(f)).map(|face| face as u8))
+        .collect()
+}
+
+fn turn(amount: u8) -> Move {
+    Move::new(U, amount)
+}
+
+// Every state of every case with all AUFs around the algorithms, keyed by
+// what `view` keeps of the last layer. A state points to the case, the AUF
+// before the algorithm and the one after.
+pub(crate) struct Cases {
+    states: HashMap<Vec<u8>, (usize, u8, u8)>
+}
+
+impl Cases {
+    pub(crate) fn new(algs: &[&str], view: impl Fn(&Cube) -> Option<Vec<u8>>, post_auf: bool) -> Self {
+        let mut states = HashMap::new();
+        let after = if post_auf { 4 } else { 1 };
+        for (case, alg) in algs.iter().enumerate() {
+            let undo = invert(&parse_alg(alg).unwrap());
+            for pre in 0..4 {
+                for post in 0..after {
+                    let mut cube = Cube::default();
+                    cube.apply(turn(4 - post));
+                    cube.apply_alg(&undo);
+                    cube.apply(turn(4 - pre));
+                    if let Some(state) = view(&cube) {
+                        states.entry(state).or_insert((case, pre, post));
+                    }
+                }
+            }
+        }
+        Cases { states }
+    }
+    pub(crate) fn find(&self, state: &[u8]) -> Option<(usize, u8, u8)> {
+        self.states.get(state).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OllCase {
+    // 0 when the last layer is oriented already
+    pub number: usize,
+    pub auf: Move,
+    // the same angle by turning the whole cube instead of U
+    pub rotation: Move
+}
+
+impl OllCase {
+    pub fn alg(&self) -> Vec<Move> {
+        match self.number {
+            0 => vec![],
+            number => parse_alg(OLL_ALGS[number - 1]).unwrap()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PllCase {
+    // "skip" when only an AUF is left
+    pub name: &'static str,
+    pub auf: Move,
+    pub rotation: Move,
+    // after the algorithm, done from the AUF angle
+    pub post_auf: Move
+}
+
+impl PllCase {
+    pub fn alg(&self) -> Vec<Move> {
+        PLL_ALGS.iter().find(|(name, _)| *name == self.name)
+            .map_or(vec![], |(_, alg)| parse_alg(alg).unwrap())
+    }
+}
+
+fn oriented(cube: &Cube) -> Option<Vec<u8>> {
+    Some(faces(cube, &LAST_LAYER)?.iter().map(|&face| (face == 0) as u8).collect())
+}
+
+fn oll_cases() -> &'static Cases {
+    static CASES: OnceLock<Cases> = OnceLock::new();
+    CASES.get_or_init(|| Cases::new(&[&[""][..], &OLL_ALGS].concat(), oriented, false))
+}
+
+fn pll_cases() -> &'static Cases {
+    static CASES: OnceLock<Cases> = OnceLock::new();
+    CASES.get_or_init(|| {
+        let algs: Vec<&str> = std::iter::once("").chain(PLL_ALGS.iter().map(|(_, alg)| *alg)).collect();
+        Cases::new(&algs, |cube| faces(cube, &LAST_LAYER), true)
+    })
+}
+
+pub fn recognise_oll(cube: &Cube) -> Option<OllCase> {
+    if !is_f2l_solved(cube) {
+        return None;
+    }
+    let (number, auf, _) = oll_cases().find(&oriented(cube)?)?;
+    Some(OllCase { number, auf: turn(auf), rotation: Move::new(Y, auf) })
+}
+
+// None as well when the last layer is not oriented
+pub fn recognise_pll(cube: &Cube) -> Option<PllCase> {
+    if !is_f2l_solved(cube) {
+        return None;
+    }
+    let (case, auf, post_auf) = pll_cases().find(&faces(cube, &LAST_LAYER)?)?;
+    Some(PllCase {
+        name: if case == 0 { "skip" } else { PLL_ALGS[case - 1].0 },
+        auf: turn(auf),
+        rotation: Move::new(Y, auf),
+        post_auf: turn(post_auf)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn case(setup: &str) -> Cube {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(setup).unwrap());
+        cube
+    }
+
+    #[test]
+    fn check_algs() {
+        let solved_f2l = |cube: &Cube| is_f2l_solved(cube) && cube.centre_colors() == Cube::default().centre_colors();
+        for alg in OLL_ALGS.iter().chain(PLL_ALGS.iter().map(|(_, alg)| alg)) {
+            let cube = case(alg);
+            assert!(solved_f2l(&cube), "{}", alg);
+        }
+        for alg in PLL_ALGS.iter().map(|(_, alg)| alg) {
+            assert_eq!(oriented(&case(alg)), oriented(&Cube::default()), "{}", alg);
+        }
+        let oll: Vec<_> = (0..=57).map(|n| oll_cases().states.values().filter(|s| s.0 == n).count()).collect();
+        assert!(oll.iter().all(|&states| states > 0));
+        assert_eq!(oll.iter().sum::<usize>(), 216);
+        let pll: Vec<_> = (0..=21).map(|n| pll_cases().states.values().filter(|s| s.0 == n).count()).collect();
+        assert!(pll.iter().all(|&states| states > 0));
+        assert_eq!(pll.iter().sum::<usize>(), 288);
+    }
+
+    #[test]
+    fn check_recognise_oll() {
+        // dot cases, then all edges oriented
+        for number in [1, 2, 3, 4, 17, 18, 19, 20] {
+            let cube = case(&format!("U2 {}", alg_of(OLL_ALGS[number - 1])));
+            assert!([(0, 4), (1, 3), (1, 5), (2, 4)].iter().all(|&f| cube.color(f) != cube.color((1, 4))));
+            assert_eq!(recognise_oll(&cube).unwrap().number, number);
+        }
+        for number in 21..=27 {
+            let cube = case(&alg_of(OLL_ALGS[number - 1]));
+            assert!([(0, 4), (1, 3), (1, 5), (2, 4)].iter().all(|&f| cube.color(f) == cube.color((1, 4))));
+        }
+        let cube = case("U' R U2 R' U' R U' R' U2");
+        let found = recognise_oll(&cube).unwrap();
+        assert_eq!(found.number, 27);
+        let mut solved = cube.clone();
+        solved.apply(found.auf);
+        solved.apply_alg(&found.alg());
+        assert_eq!(oriented(&solved), oriented(&Cube::default()));
+        let mut turned = cube.clone();
+        turned.apply(found.rotation);
+        assert_eq!(recognise_oll(&turned).unwrap().auf, turn(0));
+        assert_eq!(recognise_oll(&case("U")).unwrap().number, 0);
+        assert_eq!(recognise_oll(&case("R")), None);
+    }
+
+    #[test]
+    fn check_recognise_pll() {
+        for (name, alg) in PLL_ALGS {
+            for (pre, post) in [(0, 0), (1, 2), (3, 1)] {
+                let cube = case(&format!("{} {} {}", turn(4 - post), alg_of(alg), turn(4 - pre)));
+                let found = recognise_pll(&cube).unwrap();
+                assert_eq!(found.name, name);
+                let mut solved = cube.clone();
+                solved.apply(found.auf);
+                solved.apply_alg(&found.alg());
+                solved.apply(found.post_auf);
+                assert_eq!(solved, Cube::default(), "{}", name);
+            }
+        }
+        assert_eq!(recognise_pll(&case("U2")).unwrap().post_auf, turn(2));
+        assert_eq!(recognise_pll(&case(OLL_ALGS[20])), None);
+        let scrambled = case("R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F");
+        assert_eq!(recognise_pll(&scrambled), None);
+    }
+
+    fn alg_of(alg: &str) -> String {
+        crate::notation::alg_to_string(&invert(&parse_alg(alg).unwrap()))
+    }
+}
@@ -3,7 +3,37 @@ use std::fmt;
 use colored::*;
 use Stiker::*;
 
-#[derive(PartialEq, Clone, Copy)]
+pub mod notation;
+pub mod facelet;
+mod search;
+pub mod roux;
+pub mod zz;
+pub mod cross;
+pub mod cubie;
+pub mod thistlethwaite;
+pub mod algsearch;
+pub mod tables;
+pub mod batch;
+pub mod last_layer;
+pub mod subsets;
+pub mod f2l;
+pub mod algdb;
+pub mod random;
+pub mod trainer;
+pub mod blocks;
+pub mod bld;
+pub mod comm;
+pub mod symmetry;
+pub mod json;
+pub mod report;
+pub mod scramble;
+pub mod competition;
+pub mod quality;
+pub mod svg;
+
+use notation::{Layer, Move};
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Stiker {
     B,  // Blue
     R,  // Red
@@ -14,9 +44,9 @@ pub enum Stiker {
     V   // Void
 }
 
-impl Into<Stiker> for char {
-    fn into(self) -> Stiker {
-        match self {
+impl From<char> for Stiker {
+    fn from(letter: char) -> Stiker {
+        match letter {
             'b' => B,
             'r' => R,
             'y' => Y,
@@ -28,9 +58,9 @@ impl Into<Stiker> for char {
     }
 }
 
-impl Into<char> for Stiker {
-    fn into(self) -> char {
-        match self {
+impl From<Stiker> for char {
+    fn from(stiker: Stiker) -> char {
+        match stiker {
             B => 'b',
             R => 'r',
             Y => 'y',
@@ -44,7 +74,7 @@ impl Into<char> for Stiker {
 
 type Scan = [[Stiker; 12]; 9];
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct Cube {
     pub scan: Scan
 }
@@ -54,8 +84,8 @@ impl fmt::Debug for Cube {
         let mut result = "\n  0 2 4 6 8 10".to_string();
         let string = format!("{}", self);
         let result_chars: Vec<_> = string.lines().collect();
-        for i in 1..result_chars.len() {
-            result = format!("{}\n{} {}", result, i - 1, result_chars[i]);
+        for (i, line) in result_chars.iter().enumerate().skip(1) {
+            result = format!("{}\n{} {}", result, i - 1, line);
         }
         write!(f, "{}", result)
     }
@@ -100,7 +130,7 @@ impl Default for Cube {
 }
 
 impl Cube {
-    fn from(string: impl Into<String>) -> Self {
+    pub fn from(string: impl Into<String>) -> Self {
         let mut cube = Cube::default();
         let borrow = string.into();
         let notes = borrow.split_whitespace();
@@ -254,7 +284,54 @@ impl Cube {
     pub fn rotate_middle_inv(&mut self) {
         self.rotate_by_start_and_direction_inv(ROTATE_MIDDLE, DIRECTION_MIDDLE);
     }
-} 
+    pub fn rotate_equator(&mut self) {
+        self.rotate_by_start_and_direction(ROTATE_EQUATOR, DIRECTION_EQUATOR);
+    }
+    pub fn rotate_equator_inv(&mut self) {
+        self.rotate_by_start_and_direction_inv(ROTATE_EQUATOR, DIRECTION_EQUATOR);
+    }
+    pub fn rotate_standing(&mut self) {
+        self.rotate_by_start_and_direction(ROTATE_STANDING, DIRECTION_STANDING);
+    }
+    pub fn rotate_standing_inv(&mut self) {
+        self.rotate_by_start_and_direction_inv(ROTATE_STANDING, DIRECTION_STANDING);
+    }
+}
+
+impl Cube {
+    fn turn(&mut self, layer: Layer) {
+        match layer {
+            Layer::U => self.rotate_up(),
+            Layer::D => self.rotate_down(),
+            Layer::L => self.rotate_left(),
+            Layer::R => self.rotate_rigth(),
+            Layer::F => self.rotate_front(),
+            Layer::B => self.rotate_back(),
+            Layer::M => self.rotate_middle(),
+            Layer::E => self.rotate_equator(),
+            Layer::S => self.rotate_standing(),
+            Layer::Uw => { self.rotate_up(); self.rotate_equator_inv() },
+            Layer::Dw => { self.rotate_down(); self.rotate_equator() },
+            Layer::Lw => { self.rotate_left(); self.rotate_middle() },
+            Layer::Rw => { self.rotate_rigth(); self.rotate_middle_inv() },
+            Layer::Fw => { self.rotate_front(); self.rotate_standing() },
+            Layer::Bw => { self.rotate_back(); self.rotate_standing_inv() },
+            Layer::X => { self.rotate_rigth(); self.rotate_middle_inv(); self.rotate_left_inv() },
+            Layer::Y => { self.rotate_up(); self.rotate_equator_inv(); self.rotate_down_inv() },
+            Layer::Z => { self.rotate_front(); self.rotate_standing(); self.rotate_back_inv() }
+        }
+    }
+    pub fn apply(&mut self, m: Move) {
+        for _ in 0..m.amount {
+            self.turn(m.layer);
+        }
+    }
+    pub fn apply_alg(&mut self, alg: &[Move]) {
+        for &m in alg {
+            self.apply(m);
+        }
+    }
+}
 
 const CENTER_FRONT: (usize, usize) = (4, 4);
 const ROTATE_FRONT: [(isize, isize); 4] = [
@@ -311,12 +388,26 @@ const DIRECTION_MIDDLE: [(isize, isize); 4] = [
     (1, 0), (1, 0), (1, 0), (-1, 0)
 ];
 
+const ROTATE_EQUATOR: [(isize, isize); 4] = [
+    (4, 9), (4, 6), (4, 3), (4, 0)
+];
+const DIRECTION_EQUATOR: [(isize, isize); 4] = [
+    (0, 1); 4
+];
+
+const ROTATE_STANDING: [(isize, isize); 4] = [
+    (1, 5), (3, 1), (7, 3), (5, 7)
+];
+const DIRECTION_STANDING: [(isize, isize); 4] = [
+    (0, -1), (1, 0), (0, 1), (-1, 0)
+];
+
 #[cfg(test)]
 mod test {
     use super::*;
     
     // R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F
-    const SCRAMBLED_SCAN: [[char; 12]; 9] = [
+    pub(crate) const SCRAMBLED_SCAN: [[char; 12]; 9] = [
         [' ',' ',' ','r','g','w',' ',' ',' ',' ',' ',' '],
         [' ',' ',' ','b','y','w',' ',' ',' ',' ',' ',' '],
         [' ',' ',' ','o','y','o',' ',' ',' ',' ',' ',' '],
@@ -587,4 +678,49 @@ mod test {
             [' ',' ',' ','b','w','g',' ',' ',' ',' ',' ',' '],
         ]));
     }
+
+    #[test]
+    fn check_rotate_equator() {
+        let mut cube = Cube::default();
+        cube.rotate_equator();
+        assert_eq!(cube, Cube::from_scan([
+            [' ',' ',' ','y','y','y',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','y','y','y',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','y','y','y',' ',' ',' ',' ',' ',' '],
+            ['o','o','o','b','b','b','r','r','r','g','g','g'],
+            ['g','g','g','o','o','o','b','b','b','r','r','r'],
+            ['o','o','o','b','b','b','r','r','r','g','g','g'],
+            [' ',' ',' ','w','w','w',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','w','w','w',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','w','w','w',' ',' ',' ',' ',' ',' '],
+        ]));
+    }
+
+    #[test]
+    fn check_rotate_standing() {
+        let mut cube = Cube::default();
+        cube.rotate_standing();
+        assert_eq!(cube, Cube::from_scan([
+            [' ',' ',' ','y','y','y',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','o','o','o',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','y','y','y',' ',' ',' ',' ',' ',' '],
+            ['o','w','o','b','b','b','r','y','r','g','g','g'],
+            ['o','w','o','b','b','b','r','y','r','g','g','g'],
+            ['o','w','o','b','b','b','r','y','r','g','g','g'],
+            [' ',' ',' ','w','w','w',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','r','r','r',' ',' ',' ',' ',' ',' '],
+            [' ',' ',' ','w','w','w',' ',' ',' ',' ',' ',' '],
+        ]));
+    }
+
+    #[test]
+    fn check_apply() {
+        let mut cube = Cube::default();
+        cube.apply_alg(&notation::parse_alg("x y z r' u2 f").unwrap());
+        cube.apply_alg(&notation::invert(&notation::parse_alg("x y z r' u2 f").unwrap()));
+        assert_eq!(cube, Cube::default());
+        let mut cube = Cube::default();
+        cube.apply_alg(&notation::parse_alg("R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F").unwrap());
+        assert_eq!(cube, Cube::from_scan(SCRAMBLED_SCAN));
+    }
 }
@@ -0,0 +1,206 @@
+use std::fmt;
+use Layer::*;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
+pub enum Layer {
+    U, D, L, R, F, B,
+    M, E, S,
+    Uw, Dw, Lw, Rw, Fw, Bw,
+    X, Y, Z
+}
+
+pub const LAYERS: [Layer; 18] = [
+    U, D, L, R, F, B, M, E, S, Uw, Dw, Lw, Rw, Fw, Bw, X, Y, Z
+];
+
+impl Layer {
+    // 0 - U/D axis, 1 - R/L axis, 2 - F/B axis
+    pub fn axis(self) -> usize {
+        match self {
+            U | D | E | Uw | Dw | Y => 0,
+            L | R | M | Lw | Rw | X => 1,
+            F | B | S | Fw | Bw | Z => 2
+        }
+    }
+    pub fn is_slice(self) -> bool {
+        matches!(self, M | E | S)
+    }
+    pub fn is_rotation(self) -> bool {
+        matches!(self, X | Y | Z)
+    }
+    fn notation(self) -> &'static str {
+        match self {
+            U => "U", D => "D", L => "L", R => "R", F => "F", B => "B",
+            M => "M", E => "E", S => "S",
+            Uw => "u", Dw => "d", Lw => "l", Rw => "r", Fw => "f", Bw => "b",
+            X => "x", Y => "y", Z => "z"
+        }
+    }
+}
+
+// amount is the number of clockwise quarter turns: 1, 2 or 3 (prime)
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Move {
+    pub layer: Layer,
+    pub amount: u8
+}
+
+impl Move {
+    pub fn new(layer: Layer, amount: u8) -> Self {
+        Move { layer, amount: amount % 4 }
+    }
+    pub fn inverse(self) -> Self {
+        Move::new(self.layer, 4 - self.amount)
+    }
+    pub fn all(layers: &[Layer]) -> Vec<Move> {
+        layers.iter()
+            .flat_map(|&layer| (1..=3).map(move |amount| Move::new(layer, amount)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self.amount {
+            2 => "2",
+            3 => "'",
+            _ => ""
+        };
+        write!(f, "{}{}", self.layer.notation(), suffix)
+    }
+}
+
+impl std::str::FromStr for Move {
+    type Err = String;
+    fn from_str(note: &str) -> Result<Self, Self::Err> {
+        let (head, amount) = if let Some(head) = note.strip_suffix("2'") {
+            (head, 2)
+        } else if let Some(head) = note.strip_suffix('\'') {
+            (head, 3)
+        } else if let Some(head) = note.strip_suffix('2') {
+            (head, 2)
+        } else {
+            (note, 1)
+        };
+        if let Some(head) = head.strip_suffix('3').filter(|head| !head.is_empty()) {
+            return head.parse::<Move>()
+                .map(|m| Move::new(m.layer, 3 * amount))
+                .map_err(|_| note.to_string());
+        }
+        let layer = match head {
+            "U" => U, "D" => D, "L" => L, "R" => R, "F" => F, "B" => B,
+            "M" => M, "E" => E, "S" => S,
+            "u" | "Uw" => Uw, "d" | "Dw" => Dw, "l" | "Lw" => Lw,
+            "r" | "Rw" => Rw, "f" | "Fw" => Fw, "b" | "Bw" => Bw,
+            "x" => X, "y" => Y, "z" => Z,
+            _ => return Err(note.to_string())
+        };
+        Ok(Move::new(layer, amount))
+    }
+}
+
+pub fn parse_alg(alg: &str) -> Option<Vec<Move>> {
+    alg.split_whitespace()
+        .map(|note| note.parse().ok())
+        .collect()
+}
+
+pub fn alg_to_string(alg: &[Move]) -> String {
+    alg.iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn invert(alg: &[Move]) -> Vec<Move> {
+    alg.iter().rev().map(|m| m.inverse()).collect()
+}
+
+// merges neighbouring turns of the same layer, also across turns
+// of parallel layers, which commute with them
+pub fn simplify(alg: &[Move]) -> Vec<Move> {
+    let mut result: Vec<Move> = Vec::new();
+    for &m in alg.iter().filter(|m| m.amount != 0) {
+        let mut merged = false;
+        for i in (0..result.len()).rev() {
+            if result[i].layer.axis() != m.layer.axis() {
+                break;
+            }
+            if result[i].layer == m.layer {
+                let amount = (result[i].amount + m.amount) % 4;
+                if amount == 0 {
+                    result.remove(i);
+                } else {
+                    result[i].amount = amount;
+                }
+                merged = true;
+                break;
+            }
+        }
+        if !merged {
+            result.push(m);
+        }
+    }
+    if result.len() < alg.iter().filter(|m| m.amount != 0).count() {
+        simplify(&result)
+    } else {
+        result
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Metric {
+    Htm,  // Half turn metric, slices count twice
+    Qtm,  // Quarter turn metric, slices count twice
+    Stm,  // Slice turn metric
+    Etm   // Execution turn metric, rotations count too
+}
+
+impl Metric {
+    pub fn count(self, alg: &[Move]) -> usize {
+        alg.iter().map(|m| {
+            let layer = m.layer;
+            let quarters = if m.amount == 2 { 2 } else { 1 };
+            match self {
+                _ if self != Metric::Etm && layer.is_rotation() => 0,
+                Metric::Htm if layer.is_slice() => 2,
+                Metric::Qtm if layer.is_slice() => 2 * quarters,
+                Metric::Qtm => quarters,
+                _ => 1
+            }
+        }).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_parse_alg() {
+        let alg = parse_alg("R U2 R' r Rw2' M' x y2 E3").unwrap();
+        assert_eq!(alg, vec![
+            Move::new(R, 1), Move::new(U, 2), Move::new(R, 3), Move::new(Rw, 1),
+            Move::new(Rw, 2), Move::new(M, 3), Move::new(X, 1), Move::new(Y, 2),
+            Move::new(E, 3)
+        ]);
+        assert_eq!(alg_to_string(&alg), "R U2 R' r r2 M' x y2 E'");
+        assert_eq!(parse_alg("R Q"), None);
+    }
+
+    #[test]
+    fn check_simplify() {
+        let alg = parse_alg("R L R' U U' F2 F2 M M").unwrap();
+        assert_eq!(alg_to_string(&simplify(&alg)), "L M2");
+        assert_eq!(invert(&parse_alg("R U' M2").unwrap()), parse_alg("M2 U R'").unwrap());
+    }
+
+    #[test]
+    fn check_metric() {
+        let alg = parse_alg("R U2 M' r x E2").unwrap();
+        assert_eq!(Metric::Htm.count(&alg), 7);
+        assert_eq!(Metric::Qtm.count(&alg), 10);
+        assert_eq!(Metric::Stm.count(&alg), 5);
+        assert_eq!(Metric::Etm.count(&alg), 6);
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::cubie::{parity, CubieCube};
+use crate::last_layer::{recognise_oll, recognise_pll};
+use crate::notation::Move;
+
+// A test fails when a uniform generator gives a worse statistic this
+// rarely, one run in a thousand
+pub const SIGNIFICANCE: f64 = 0.001;
+
+// Cells expected fewer times than this are pooled, the chi-square
+// approximation is off for them
+const MIN_EXPECTED: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub name: &'static str,
+    pub statistic: f64,
+    pub degrees: usize,
+    pub p_value: f64,
+    pub passed: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityReport {
+    pub samples: usize,
+    pub distinct_states: usize,
+    // the states, that came up once, twice and so on
+    pub repeats: Vec<usize>,
+    pub tests: Vec<TestResult>
+}
+
+impl QualityReport {
+    pub fn passed(&self) -> bool {
+        self.tests.iter().all(|t| t.passed)
+    }
+    // samples, that gave a state an earlier one gave
+    pub fn collisions(&self) -> usize {
+        self.samples - self.distinct_states
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} scrambles, {} distinct states, {} collisions", self.samples, self.distinct_states,
+            self.collisions())?;
+        for test in &self.tests {
+            writeln!(f, "{}: chi-square {:.2} on {} degrees, p = {:.4} {}", test.name, test.statistic,
+                test.degrees, test.p_value, if test.passed { "pass" } else { "FAIL" })?;
+        }
+        write!(f, "{}", if self.passed() { "PASS" } else { "FAIL" })
+    }
+}
+
+// Lanczos, good to about 15 digits for x > 0
+fn ln_gamma(x: f64) -> f64 {
+    const C: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = C.iter().enumerate().skip(1).fold(C[0], |sum, (i, c)| sum + c / (x + i as f64));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// Q(a, x), the regularised upper incomplete gamma function: the series
+// below a + 1, the continued fraction above
+fn upper_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let front = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term < sum * 1e-15 {
+                break;
+            }
+        }
+        return (1.0 - front * sum).max(0.0);
+    }
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        d = if d.abs() < tiny { 1.0 / tiny } else { 1.0 / d };
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    front * h
+}
+
+// The chance of a chi-square this large or larger
+pub fn p_value(statistic: f64, degrees: usize) -> f64 {
+    upper_gamma(degrees as f64 / 2.0, statistic / 2.0)
+}
+
+fn result(name: &'static str, statistic: f64, degrees: usize) -> TestResult {
+    let p_value = p_value(statistic, degrees);
+    TestResult { name, statistic, degrees, p_value, passed: p_value >= SIGNIFICANCE }
+}
+
+// Pearson's test of counts against probabilities, rare cells pooled
+fn goodness_of_fit<K: Eq + std::hash::Hash>(
+    name: &'static str,
+    counts: &HashMap<K, usize>,
+    probabilities: &HashMap<K, f64>
+) -> TestResult {
+    let samples = counts.values().sum::<usize>() as f64;
+    let mut cells = Vec::new();
+    let mut pooled = (0.0, 0.0);
+    for (key, p) in probabilities {
+        let observed = counts.get(key).copied().unwrap_or(0) as f64;
+        if p * samples < MIN_EXPECTED {
+            pooled = (pooled.0 + observed, pooled.1 + p * samples);
+        } else {
+            cells.push((observed, p * samples));
+        }
+    }
+    if pooled.1 > 0.0 {
+        cells.push(pooled);
+    }
+    let statistic = cells.iter().map(|(o, e)| (o - e) * (o - e) / e).sum();
+    result(name, statistic, cells.len().saturating_sub(1))
+}
+
+// Where each piece ends up. Row and column sums are fixed, so the
+// statistic of a uniform permutation is n / (n - 1) times a chi-square
+// on (n - 1)^2 degrees.
+fn positions(name: &'static str, counts: &[Vec<usize>]) -> TestResult {
+    let n = counts.len() as f64;
+    let expected = counts[0].iter().sum::<usize>() as f64 / n;
+    let statistic: f64 = counts.iter().flatten().map(|&o| (o as f64 - expected).powi(2) / expected).sum();
+    result(name, statistic * (n - 1.0) / n, (counts.len() - 1).pow(2))
+}
+
+// The order of four pieces among themselves
+fn ranks(pieces: &[u8]) -> [u8; 4] {
+    std::array::from_fn(|i| pieces[..4].iter().filter(|&&p| p < pieces[i]).count() as u8)
+}
+
+// The last layer a solve of the state would come to: orientations of
+// three U slots with the fourth to match, and the order of the U pieces
+// with two edges swapped when the parities differ. Either is uniform when
+// the state is.
+fn last_layers(cubie: &CubieCube) -> (CubieCube, CubieCube) {
+    let mut oll = CubieCube::default();
+    oll.co[..3].copy_from_slice(&cubie.co[..3]);
+    oll.co[3] = (6 - oll.co[..3].iter().sum::<u8>() % 3) % 3;
+    oll.eo[..3].copy_from_slice(&cubie.eo[..3]);
+    oll.eo[3] = oll.eo[..3].iter().sum::<u8>() % 2;
+    let mut pll = CubieCube::default();
+    pll.cp[..4].copy_from_slice(&ranks(&cubie.cp));
+    pll.ep[..4].copy_from_slice(&ranks(&cubie.ep));
+    if parity(&pll.cp[..4]) != parity(&pll.ep[..4]) {
+        pll.ep.swap(2, 3);
+    }
+    (oll, pll)
+}
+
+fn oll_case(oll: &CubieCube) -> usize {
+    recognise_oll(&oll.to_cube()).map_or(usize::MAX, |c| c.number)
+}
+
+fn pll_case(pll: &CubieCube) -> &'static str {
+    recognise_pll(&pll.to_cube()).map_or("?", |c| c.name)
+}
+
+// Case chances over every last layer, each as likely as the others
+fn case_probabilities() -> (HashMap<usize, f64>, HashMap<&'static str, f64>) {
+    let mut oll = HashMap::new();
+    for pattern in 0..27 * 8 {
+        let mut cubie = CubieCube::default();
+        for slot in 0..3 {
+            cubie.co[slot] = (pattern / 3usize.pow(slot as u32) % 3) as u8;
+            cubie.eo[slot] = ((pattern / 27) >> slot & 1) as u8;
+        }
+        let (cubie, _) = last_layers(&cubie);
+        *oll.entry(oll_case(&cubie)).or_insert(0.0) += 1.0 / 216.0;
+    }
+    let orders: Vec<[u8; 4]> = (0..256)
+        .map(|i| std::array::from_fn(|j| (i >> (2 * j) & 3) as u8))
+        .filter(|order: &[u8; 4]| order.iter().collect::<HashSet<_>>().len() == 4)
+        .collect();
+    let mut pll = HashMap::new();
+    for corners in &orders {
+        for edges in orders.iter().filter(|edges| parity(*edges) == parity(corners)) {
+            let mut cubie = CubieCube::default();
+            cubie.cp[..4].copy_from_slice(corners);
+            cubie.ep[..4].copy_from_slice(edges);
+            *pll.entry(pll_case(&cubie)).or_insert(0.0) += 1.0 / 288.0;
+        }
+    }
+    (oll, pll)
+}
+
+// Runs the generator `count` times and tests the states it comes to
+pub fn check_scrambles(count: usize, mut scramble: impl FnMut() -> Vec<Move>) -> QualityReport {
+    let mut states = HashMap::new();
+    let mut oll_counts = HashMap::new();
+    let mut pll_counts = HashMap::new();
+    let mut corners = vec![vec![0; 8]; 8];
+    let mut edges = vec![vec![0; 12]; 12];
+    for _ in 0..count {
+        let mut cubie = CubieCube::default();
+        cubie.apply_alg(&scramble());
+        *states.entry(cubie).or_insert(0) += 1;
+        let (oll, pll) = last_layers(&cubie);
+        *oll_counts.entry(oll_case(&oll)).or_insert(0) += 1;
+        *pll_counts.entry(pll_case(&pll)).or_insert(0) += 1;
+        for (slot, &piece) in cubie.cp.iter().enumerate() {
+            corners[piece as usize][slot] += 1;
+        }
+        for (slot, &piece) in cubie.ep.iter().enumerate() {
+            edges[piece as usize][slot] += 1;
+        }
+    }
+    let (oll, pll) = case_probabilities();
+    let mut repeats = vec![0; states.values().max().copied().unwrap_or(0)];
+    for times in states.values() {
+        repeats[times - 1] += 1;
+    }
+    QualityReport {
+        samples: count,
+        distinct_states: states.len(),
+        repeats,
+        tests: vec![
+            goodness_of_fit("OLL cases", &oll_counts, &oll),
+            goodness_of_fit("PLL cases", &pll_counts, &pll),
+            positions("corner positions", &corners),
+            positions("edge positions", &edges)
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+    use crate::random::SplitMix;
+    use crate::scramble::{random_moves, random_state};
+
+    #[test]
+    fn check_p_value() {
+        assert!((p_value(3.841, 1) - 0.05).abs() < 1e-3);
+        assert!((p_value(18.307, 10) - 0.05).abs() < 1e-3);
+        assert!((p_value(2.0, 2) - (-1.0f64).exp()).abs() < 1e-9);
+        assert!((p_value(100.0, 4) - 0.0).abs() < 1e-12);
+        assert_eq!(p_value(0.0, 3), 1.0);
+    }
+
+    #[test]
+    fn check_probabilities() {
+        let (oll, pll) = case_probabilities();
+        assert_eq!((oll.len(), pll.len()), (58, 22));
+        assert!((oll[&0] * 216.0 - 1.0).abs() < 1e-9 && (pll["skip"] * 288.0 - 4.0).abs() < 1e-9);
+        assert!((oll.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn check_generators() {
+        let mut random = SplitMix::new(11);
+        let report = check_scrambles(1500, || random_moves(&mut random, 25));
+        assert!(report.passed(), "{}", report);
+        assert_eq!((report.distinct_states, report.repeats.clone()), (1500, vec![1500]));
+        let short = check_scrambles(1500, || random_moves(&mut random, 3));
+        assert!(!short.passed() && short.collisions() > 0);
+        assert_eq!(short.repeats.iter().enumerate().map(|(i, n)| (i + 1) * n).sum::<usize>(), 1500);
+        assert_eq!(short.repeats.iter().sum::<usize>(), short.distinct_states);
+        assert!(short.to_string().ends_with("FAIL"));
+        // each sample is a solve, so fewer of them
+        let states = check_scrambles(300, || random_state(&mut random));
+        assert!(states.passed(), "{}", states);
+        assert_eq!((states.collisions(), states.repeats.clone()), (0, vec![300]));
+    }
+
+    #[test]
+    fn check_repeats() {
+        let scrambles = ["R", "U", "R", "F", "R", "U"].map(|s| parse_alg(s).unwrap());
+        let mut next = scrambles.iter().cycle();
+        let report = check_scrambles(6, || next.next().unwrap().clone());
+        assert_eq!((report.distinct_states, report.collisions()), (3, 3));
+        assert_eq!(report.repeats, [1, 1, 1]);
+        assert!(report.to_string().starts_with("6 scrambles, 3 distinct states, 3 collisions"));
+    }
+}
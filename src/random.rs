@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Where the trainer and the scramblers take their randomness from
+pub trait Random {
+    fn next_u64(&mut self) -> u64;
+
+    // Uniform in 0..n. Draws below 2^64 mod n are thrown away, what is left
+    // is a whole number of runs of n, so no value comes up more often.
+    fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0);
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let draw = self.next_u64();
+            if draw >= threshold {
+                return draw % n;
+            }
+        }
+    }
+
+    // Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// SplitMix64: quick and repeatable from its seed, fine for practice
+#[derive(Debug, Clone)]
+pub struct SplitMix(u64);
+
+impl SplitMix {
+    pub fn new(seed: u64) -> Self {
+        SplitMix(seed)
+    }
+    pub fn from_time() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        SplitMix(now)
+    }
+}
+
+impl Random for SplitMix {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+// The ChaCha20 block of RFC 8439, `counter` is the counter and the nonce
+fn chacha_block(key: &[u32; 8], counter: [u32; 4]) -> [u32; 16] {
+    let mut input = [0; 16];
+    input[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    input[4..12].copy_from_slice(key);
+    input[12..].copy_from_slice(&counter);
+    let mut state = input;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    for (s, i) in state.iter_mut().zip(input) {
+        *s = s.wrapping_add(i);
+    }
+    state
+}
+
+// ChaCha20 keystream: nobody can tell what comes next without the key,
+// for official scrambles. Blocks are counted in 64 bits with no nonce.
+#[derive(Clone)]
+pub struct ChaCha {
+    key: [u32; 8],
+    counter: u64,
+    block: [u32; 16],
+    used: usize
+}
+
+impl ChaCha {
+    pub fn new(key: [u8; 32]) -> Self {
+        let mut words = [0; 8];
+        for (word, bytes) in words.iter_mut().zip(key.chunks(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        ChaCha { key: words, counter: 0, block: [0; 16], used: 16 }
+    }
+    // Keyed from the operating system, an error rather than a weak key
+    // where it has no /dev/urandom
+    pub fn from_entropy() -> io::Result<Self> {
+        let mut key = [0; 32];
+        File::open("/dev/urandom")?.read_exact(&mut key)?;
+        Ok(ChaCha::new(key))
+    }
+    fn next_u32(&mut self) -> u32 {
+        if self.used == 16 {
+            let counter = [self.counter as u32, (self.counter >> 32) as u32, 0, 0];
+            self.block = chacha_block(&self.key, counter);
+            self.counter += 1;
+            self.used = 0;
+        }
+        self.used += 1;
+        self.block[self.used - 1]
+    }
+}
+
+impl Random for ChaCha {
+    fn next_u64(&mut self) -> u64 {
+        self.next_u32() as u64 | (self.next_u32() as u64) << 32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Draws(Vec<u64>);
+
+    impl Random for Draws {
+        fn next_u64(&mut self) -> u64 {
+            self.0.remove(0)
+        }
+    }
+
+    // Pearson's statistic against equal counts
+    fn chi_square(counts: &[usize]) -> f64 {
+        let expected = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+        counts.iter().map(|&c| (c as f64 - expected).powi(2) / expected).sum()
+    }
+
+    #[test]
+    fn check_split_mix() {
+        let mut a = SplitMix::new(7);
+        let mut b = SplitMix::new(7);
+        assert!((0..10).all(|_| a.next_u64() == b.next_u64()));
+        let mut counts = [0; 6];
+        for _ in 0..6000 {
+            counts[a.below(6) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (800..1200).contains(&c)), "{:?}", counts);
+        assert!((0..100).map(|_| a.unit()).all(|u| (0.0..1.0).contains(&u)));
+    }
+
+    #[test]
+    fn check_chacha() {
+        // RFC 8439, 2.3.2
+        let key: [u32; 8] = std::array::from_fn(|i| u32::from_le_bytes(std::array::from_fn(|j| (4 * i + j) as u8)));
+        let block = chacha_block(&key, [1, 0x09000000, 0x4a000000, 0]);
+        assert_eq!(block[..4], [0xe4e7f110, 0x15593bd1, 0x1fdd0f50, 0xc47120a3]);
+        assert_eq!(block[12..], [0xd19c12b5, 0xb94e16de, 0xe883d0cb, 0x4e3c50a2]);
+        let mut a = ChaCha::new([7; 32]);
+        let mut b = ChaCha::new([7; 32]);
+        assert!((0..20).all(|_| a.next_u64() == b.next_u64()));
+        assert_ne!(ChaCha::new([8; 32]).next_u64(), ChaCha::new([7; 32]).next_u64());
+        let mut entropy = ChaCha::from_entropy().unwrap();
+        assert_ne!(entropy.next_u64(), ChaCha::from_entropy().unwrap().next_u64());
+    }
+
+    #[test]
+    fn check_unbiased() {
+        // 2^64 mod 3 is 1, so a draw of 0 would make 0 come up more often
+        assert_eq!(Draws(vec![0, 0, 5]).below(3), 2);
+        assert_eq!(Draws(vec![u64::MAX]).below(3), u64::MAX % 3);
+        assert_eq!(Draws(vec![5, 1 << 62]).below(3 << 62), 1 << 62);
+        assert_eq!(Draws(vec![5]).below(1 << 63), 5);
+        // faces, amounts and the sizes of the state coordinates, the 1%
+        // points of chi-square for their degrees of freedom
+        let mut random = ChaCha::new([1; 32]);
+        for (n, critical) in [(6, 15.09), (3, 9.21), (2, 6.63), (8, 18.48), (12, 24.72), (18, 33.41)] {
+            let mut counts = vec![0; n];
+            for _ in 0..600 * n {
+                counts[random.below(n as u64) as usize] += 1;
+            }
+            assert!(chi_square(&counts) < critical, "{} {:?}", n, counts);
+        }
+        let mut buckets = [0; 10];
+        for _ in 0..5000 {
+            buckets[(random.unit() * 10.0) as usize] += 1;
+        }
+        assert!(chi_square(&buckets) < 21.67, "{:?}", buckets);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Cube;
+use crate::blocks::{BlockKind, find_blocks};
+use crate::cross;
+use crate::cubie::{self, CubieCube};
+use crate::json::{ToJson, write_object};
+use crate::last_layer::{is_f2l_solved, recognise_oll, recognise_pll};
+use crate::notation::{Layer::*, Move, simplify};
+use crate::thistlethwaite;
+use crate::zz::{Axis, misoriented_edges};
+
+// The exact distance is only looked for up to this many moves
+const SEARCH_DEPTH: usize = 8;
+
+// Half of all states are 18 moves from solved, past SEARCH_DEPTH the
+// estimate takes that unless the bounds say otherwise
+const TYPICAL_DISTANCE: usize = 18;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossLength {
+    pub color: char,
+    pub moves: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCounts {
+    pub pairs: usize,
+    pub squares: usize,
+    pub blocks_222: usize,
+    pub blocks_123: usize,
+    pub blocks_223: usize,
+    pub f2l_minus_slot: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyReport {
+    // optimal cross for each bottom colour
+    pub crosses: Vec<CrossLength>,
+    pub blocks: BlockCounts,
+    // misoriented edges read on the F/B, R/L and U/D axes
+    pub misoriented_edges: [usize; 3],
+    // corners with the U/D sticker off U and D
+    pub twisted_corners: usize,
+    pub skipped: Vec<String>,
+    // what the longest optimal cross and the pattern tables rule out, and
+    // the Thistlethwaite solution. Both are the exact distance, when it is
+    // SEARCH_DEPTH or less.
+    pub distance_lower: usize,
+    pub distance_upper: usize,
+    // the exact distance up to SEARCH_DEPTH, past that the typical one
+    // held between the bounds
+    pub estimated_distance: usize
+}
+
+// None if the stickers are not a solvable cube
+pub fn difficulty(cube: &Cube) -> Option<DifficultyReport> {
+    let cubie = CubieCube::from_cube(cube).filter(|c| c.is_solvable())?;
+    let crosses: Vec<CrossLength> = cross::analyse(cube)?.iter()
+        .map(|a| CrossLength { color: char::from(a.bottom), moves: a.cross.len() })
+        .collect();
+    let found = find_blocks(cube);
+    let count = |kind| found.iter().filter(|b| b.kind == kind).count();
+    let blocks = BlockCounts {
+        pairs: count(BlockKind::Pair),
+        squares: count(BlockKind::Square),
+        blocks_222: count(BlockKind::Block222),
+        blocks_123: count(BlockKind::Block123),
+        blocks_223: count(BlockKind::Block223),
+        f2l_minus_slot: count(BlockKind::F2lMinusSlot)
+    };
+    let axes = [(Axis::FB, "F/B"), (Axis::RL, "R/L"), (Axis::UD, "U/D")];
+    let misoriented = axes.map(|(axis, _)| misoriented_edges(cube, axis).len());
+    let mut skipped: Vec<String> = crosses.iter()
+        .filter(|c| c.moves == 0)
+        .map(|c| format!("cross {}", c.color))
+        .collect();
+    for ((_, name), count) in axes.iter().zip(misoriented) {
+        if count == 0 {
+            skipped.push(format!("EO {}", name));
+        }
+    }
+    if is_f2l_solved(cube) {
+        skipped.push("F2L".to_string());
+        if recognise_oll(cube).is_some_and(|c| c.number == 0) {
+            skipped.push("OLL".to_string());
+        }
+        if recognise_pll(cube).is_some_and(|c| c.name == "skip") {
+            skipped.push("PLL".to_string());
+        }
+    }
+    let mut distance_lower = crosses.iter().map(|c| c.moves).max().unwrap_or(0)
+        .max(cubie::distance_bound(&cubie) as usize);
+    let mut distance_upper = simplify(&thistlethwaite::solve(cube)?.moves()).len();
+    if distance_lower <= SEARCH_DEPTH {
+        let moves = Move::all(&[U, D, L, R, F, B]);
+        let solved = |c: &CubieCube| *c == CubieCube::default();
+        match cubie::search(&cubie, &moves, &cubie::distance_bound, &solved, SEARCH_DEPTH) {
+            Some(path) => (distance_lower, distance_upper) = (path.len(), path.len()),
+            None => distance_lower = SEARCH_DEPTH + 1
+        }
+    }
+    Some(DifficultyReport {
+        crosses,
+        blocks,
+        misoriented_edges: misoriented,
+        twisted_corners: cubie.co.iter().filter(|&&twist| twist != 0).count(),
+        skipped,
+        distance_lower,
+        distance_upper,
+        estimated_distance: TYPICAL_DISTANCE.clamp(distance_lower, distance_upper)
+    })
+}
+
+impl ToJson for CrossLength {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[("color", &self.color), ("moves", &self.moves)]);
+    }
+}
+
+impl ToJson for BlockCounts {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[
+            ("pairs", &self.pairs),
+            ("squares", &self.squares),
+            ("blocks_222", &self.blocks_222),
+            ("blocks_123", &self.blocks_123),
+            ("blocks_223", &self.blocks_223),
+            ("f2l_minus_slot", &self.f2l_minus_slot)
+        ]);
+    }
+}
+
+impl ToJson for DifficultyReport {
+    fn write_json(&self, out: &mut String) {
+        write_object(out, &[
+            ("crosses", &self.crosses),
+            ("blocks", &self.blocks),
+            ("misoriented_edges", &self.misoriented_edges.to_vec()),
+            ("twisted_corners", &self.twisted_corners),
+            ("skipped", &self.skipped),
+            ("distance_lower", &self.distance_lower),
+            ("distance_upper", &self.distance_upper),
+            ("estimated_distance", &self.estimated_distance)
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stiker;
+
+    #[test]
+    fn check_solved() {
+        let report = difficulty(&Cube::default()).unwrap();
+        assert!(report.crosses.iter().all(|c| c.moves == 0));
+        assert_eq!(report.crosses[5].color, char::from(Stiker::W));
+        assert_eq!(report.blocks.blocks_222, 8);
+        assert_eq!((report.twisted_corners, report.estimated_distance), (0, 0));
+        for step in ["cross w", "EO F/B", "F2L", "OLL", "PLL"] {
+            assert!(report.skipped.iter().any(|s| s == step), "{}", step);
+        }
+    }
+
+    #[test]
+    fn check_scrambled() {
+        let report = difficulty(&Cube::from("R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F")).unwrap();
+        assert!(report.distance_lower <= report.estimated_distance);
+        assert!(report.estimated_distance <= report.distance_upper);
+        assert_eq!(report.estimated_distance, TYPICAL_DISTANCE);
+        assert!(report.skipped.is_empty());
+        assert!(report.misoriented_edges.iter().all(|&n| n % 2 == 0));
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"crosses":[{"color":"y","moves":"#));
+        assert!(json.contains(r#""skipped":[]"#));
+        let near = difficulty(&Cube::from("R U")).unwrap();
+        assert_eq!((near.distance_lower, near.distance_upper, near.estimated_distance), (2, 2, 2));
+        for (alg, distance) in [("R U R' U'", 4), ("F R U R' U' F'", 6), ("R U R' U R U2 R'", 7)] {
+            let report = difficulty(&Cube::from(alg)).unwrap();
+            assert_eq!((report.distance_lower, report.estimated_distance), (distance, distance), "{}", alg);
+        }
+        let far = difficulty(&Cube::from("R U R' U' R' F R2 U' R' U' R U R' F'")).unwrap();
+        assert!(far.distance_lower > SEARCH_DEPTH && far.distance_upper >= 14);
+        assert_eq!(far.estimated_distance, TYPICAL_DISTANCE.clamp(far.distance_lower, far.distance_upper));
+        assert_eq!(near.misoriented_edges, [0, 4, 4]);
+        assert!(difficulty(&Cube { scan: [[Stiker::V; 12]; 9] }).is_none());
+    }
+}
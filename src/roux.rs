@@ -0,0 +1,320 @@
+use std::fmt;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::facelet::*;
+use crate::notation::{Layer::*, Metric, Move, alg_to_string, parse_alg, simplify};
+use crate::search::{Goal, Pruning, Search, breadth_first};
+use crate::tables::cached;
+
+// Blocks are built on L and R, with the usual white bottom and yellow
+// top. A cube with other centres on L and R is turned first.
+
+const FIRST_BLOCK_EDGES: [usize; 3] = [6, 9, 10];     // DL FL BL
+const FIRST_BLOCK_CORNERS: [usize; 2] = [5, 6];       // DLF DBL
+const SQUARE_EDGES: [usize; 2] = [4, 11];             // DR BR
+const SQUARE_CORNERS: [usize; 1] = [7];               // DRB
+const PAIR_EDGES: [usize; 1] = [8];                   // FR
+const PAIR_CORNERS: [usize; 1] = [4];                 // DFR
+const TOP_CORNERS: [usize; 4] = [0, 1, 2, 3];
+const LSE_EDGES: [usize; 6] = [0, 1, 2, 3, 5, 7];     // UR UF UL UB DF DB
+
+const ROTATIONS: [&str; 6] = ["", "y", "y'", "y2", "z", "z'"];
+
+// One alg for each CMLL case, named after the corner orientation and
+// what the side stickers show
+pub const CMLL_ALGS: [(&str, &str); 42] = [
+    ("O Adjacent", "R U R' F' R U R' U' R' F R2 U' R'"),
+    ("O Diagonal", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("H Columns", "R U2 R' U' R U R' U' R U' R'"),
+    ("H Rows", "F R U R' U' R U R' U' R U R' U' F'"),
+    ("H Column", "R U2 R2 F R F' U2 R' F R F'"),
+    ("H Row", "r U' r2 D' r U' r' D r2 U r'"),
+    ("Pi Right Bar", "F R U R' U' R U R' U' F'"),
+    ("Pi Back Slash", "F R' F' R U2 R U' R' U R U2 R'"),
+    ("Pi X Checkerboard", "R' F R U F U' R U R' U' F'"),
+    ("Pi Forward Slash", "R U2 R' U' R U R' U2 R' F R F'"),
+    ("Pi Columns", "r U' r2 D' r U r' D r2 U r'"),
+    ("Pi Left Bar", "R' U' R' F R F' R U' R' U2 R"),
+    ("U Forward Slash", "R2 D R' U2 R D' R' U2 R'"),
+    ("U Back Slash", "R2 D' R U2 R' D R U2 R"),
+    ("U Front Row", "R' U2 R U R' U R U R' U' R U' R' U2 R"),
+    ("U Rows", "F R2 D R' U R D' R2 U' F'"),
+    ("U X Checkerboard", "r U' r' U r' D' r U' r' D r"),
+    ("U Back Row", "F R U R' U' F'"),
+    ("T Left Bar", "R U R' U' R' F R F'"),
+    ("T Right Bar", "L' U' L U L F' L' F"),
+    ("T Rows", "F R' F R2 U' R' U' R U R' F2"),
+    ("T Front Row", "r' U r U2 R2 F R F' R"),
+    ("T Back Row", "r' D' r U r' D r U' r U r'"),
+    ("T Columns", "r2 D' r U r' D r2 U' r' U' r"),
+    ("S Left Bar", "R U R' U R U2 R'"),
+    ("S X Checkerboard", "L' U2 L U2 L F' L' F"),
+    ("S Forward Slash", "F R' F' R U2 R U2 R'"),
+    ("S Columns", "R2 D' R U' R' D R U' R U R' U R"),
+    ("S Right Bar", "R U R' U R' F R F' R U2 R'"),
+    ("S Back Slash", "R U' L' U R' U' L"),
+    ("AS Right Bar", "R' U' R U' R' U2 R"),
+    ("AS Columns", "R2 D R' U R D' R' U R' U' R U' R'"),
+    ("AS Back Slash", "F' L F L' U2 L' U2 L"),
+    ("AS X Checkerboard", "R U2 R' U2 R' F R F'"),
+    ("AS Forward Slash", "L' U R U' L U R'"),
+    ("AS Left Bar", "R' U' R U' R' U R' F R F' U R"),
+    ("L Mirror", "F R U' R' U' R U R' F'"),
+    ("L Inverse", "F R' F' R U R U' R'"),
+    ("L Pure", "R U2 R' U' R U R' U' R U R' U' R U' R'"),
+    ("L Front Commutator", "R U2 R D R' U2 R D' R2"),
+    ("L Diag", "R' U' R U R' F' R U R' U' R' F R2"),
+    ("L Back Commutator", "R' U2 R' D' R U2 R' D R2")
+];
+
+#[derive(Debug, Clone)]
+pub struct RouxStep {
+    pub name: &'static str,
+    pub case: Option<String>,
+    pub moves: Vec<Move>
+}
+
+impl RouxStep {
+    pub fn stm(&self) -> usize {
+        Metric::Stm.count(&self.moves)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RouxSolution {
+    pub steps: Vec<RouxStep>
+}
+
+impl RouxSolution {
+    pub fn moves(&self) -> Vec<Move> {
+        self.steps.iter().flat_map(|step| step.moves.clone()).collect()
+    }
+    pub fn stm(&self) -> usize {
+        self.steps.iter().map(|step| step.stm()).sum()
+    }
+}
+
+impl fmt::Display for RouxSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            let name = match &step.case {
+                Some(case) => format!("{} ({})", step.name, case),
+                None => step.name.to_string()
+            };
+            writeln!(f, "{}: {} ({} STM)", name, alg_to_string(&step.moves), step.stm())?;
+        }
+        write!(f, "Total: {} STM", self.stm())
+    }
+}
+
+fn pieces(cube: &Cube, edges: &[usize], corners: &[usize]) -> Option<Vec<u8>> {
+    let mut stickers = Vec::new();
+    for &edge in edges {
+        stickers.push(cube.edge_sticker(edge)?);
+    }
+    for &corner in corners {
+        stickers.push(cube.corner_sticker(corner)?);
+    }
+    Some(stickers)
+}
+
+fn homes(edges: &[usize], corners: &[usize]) -> Vec<u8> {
+    edges.iter().map(|&e| edge_home(e))
+        .chain(corners.iter().map(|&c| corner_home(c)))
+        .collect()
+}
+
+fn first_block(cube: &Cube) -> Option<Vec<Move>> {
+    static TABLES: OnceLock<[Pruning; 2]> = OnceLock::new();
+    let moves = Move::all(&[U, D, L, R, F, B, M]);
+    let goal = homes(&FIRST_BLOCK_EDGES, &FIRST_BLOCK_CORNERS);
+    let tables = TABLES.get_or_init(|| cached("roux_first_block", || [
+        Pruning::new(vec![0, 1, 2], &goal, &moves, 20),
+        Pruning::new(vec![0, 3, 4], &goal, &moves, 20)
+    ]));
+    let start = pieces(cube, &FIRST_BLOCK_EDGES, &FIRST_BLOCK_CORNERS)?;
+    Search { moves: &moves, tables, goal: &|s| s == goal }.first(&start, 16)
+}
+
+fn second_block(cube: &Cube) -> Option<Vec<Move>> {
+    static TABLES: OnceLock<[Pruning; 2]> = OnceLock::new();
+    let moves = Move::all(&[U, R, M, Rw]);
+    let edges = [&SQUARE_EDGES[..], &PAIR_EDGES].concat();
+    let corners = [&SQUARE_CORNERS[..], &PAIR_CORNERS].concat();
+    // edges DR BR FR, then corners DRB DFR
+    let goal = homes(&edges, &corners);
+    let tables = TABLES.get_or_init(|| cached("roux_second_block", || [
+        Pruning::new(vec![0, 1, 3], &goal, &moves, 20),
+        Pruning::new(vec![2, 4], &goal, &moves, 20)
+    ]));
+    let mut start = pieces(cube, &edges, &corners)?;
+    let square = Search {
+        moves: &moves,
+        tables: &tables[..1],
+        goal: &|s| [s[0], s[1], s[3]] == [goal[0], goal[1], goal[3]]
+    }.first(&start, 16)?;
+    for &m in &square {
+        apply_to_stickers(&mut start, m);
+    }
+    let pair = Search { moves: &moves, tables, goal: &|s| s == goal }.first(&start, 16)?;
+    Some(simplify(&[square, pair].concat()))
+}
+
+fn after(stickers: &[u8], alg: &[Move]) -> Vec<u8> {
+    let mut stickers = stickers.to_vec();
+    for &m in alg {
+        apply_to_stickers(&mut stickers, m);
+    }
+    stickers
+}
+
+// The first alg from the table that solves the corners, with the AUFs
+// around it
+fn cmll(cube: &Cube) -> Option<(String, Vec<Move>)> {
+    let start = pieces(cube, &[], &TOP_CORNERS)?;
+    let goal = homes(&[], &TOP_CORNERS);
+    let cases = [&[("skip", "")][..], &CMLL_ALGS].concat();
+    for (case, alg) in cases {
+        let alg = parse_alg(alg)?;
+        for pre in 0..4 {
+            for post in 0..4 {
+                let alg = simplify(&[
+                    vec![Move::new(U, pre)], alg.clone(), vec![Move::new(U, post)]
+                ].concat());
+                if after(&start, &alg) == goal {
+                    return Some((case.to_string(), alg));
+                }
+            }
+        }
+    }
+    None
+}
+
+// Every last six edges state holds the six edges, the U centre and
+// the URF corner, which tells if the U layer is aligned
+fn oriented(s: &[u8]) -> bool {
+    s[..6].iter().all(|&e| facelet(e).0 <= 2 || facelet(e).0 >= 6)
+        && [1, 7].contains(&facelet(s[6]).0)
+}
+
+fn last_six_edges(cube: &Cube) -> Option<Vec<(&'static str, Vec<Move>)>> {
+    let mut start = pieces(cube, &LSE_EDGES, &[0])?;
+    start.insert(6, cube.center_sticker(0)?);
+    let goal = homes(&LSE_EDGES, &[0]);
+    let mut goal = goal.clone();
+    goal.insert(6, center_home(0));
+    let bottom = [sticker((6, 4)), sticker((8, 4))];
+    let moves = Move::all(&[U, M]);
+    let steps: [(&str, Goal); 3] = [
+        ("EOLR", &|s| oriented(s) && bottom.contains(&s[0]) && bottom.contains(&s[2])),
+        ("UL/UR", &|s| oriented(s) && [s[0], s[2], s[7]] == [goal[0], goal[2], goal[7]]),
+        ("4c", &|s| s == goal)
+    ];
+    let mut result = Vec::new();
+    for (name, step) in steps {
+        let alg = breadth_first(&start, &moves, step, 20)?;
+        for &m in &alg {
+            apply_to_stickers(&mut start, m);
+        }
+        result.push((name, alg));
+    }
+    Some(result)
+}
+
+pub fn solve(cube: &Cube) -> Option<RouxSolution> {
+    // the M slice may sit anywhere, so the L centre is all that matters
+    let rotation = ROTATIONS.iter()
+        .map(|r| parse_alg(r).unwrap())
+        .find(|r| {
+            let mut turned = cube.clone();
+            turned.apply_alg(r);
+            turned.center_sticker(1) == Some(center_home(1))
+        })?;
+    let mut cube = cube.clone();
+    cube.apply_alg(&rotation);
+    let mut steps = Vec::new();
+    let first = first_block(&cube)?;
+    cube.apply_alg(&first);
+    steps.push(RouxStep { name: "First block", case: None, moves: [rotation, first].concat() });
+    let second = second_block(&cube)?;
+    cube.apply_alg(&second);
+    steps.push(RouxStep { name: "Second block", case: None, moves: second });
+    let (case, corners) = cmll(&cube)?;
+    cube.apply_alg(&corners);
+    steps.push(RouxStep { name: "CMLL", case: Some(case), moves: corners });
+    for (name, moves) in last_six_edges(&cube)? {
+        steps.push(RouxStep { name, case: None, moves });
+    }
+    Some(RouxSolution { steps })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::invert;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+
+    #[test]
+    fn check_solve() {
+        let mut cube = Cube::from(SCRAMBLE);
+        let solution = solve(&cube).unwrap();
+        let names: Vec<_> = solution.steps.iter().map(|s| s.name).collect();
+        assert_eq!(names, ["First block", "Second block", "CMLL", "EOLR", "UL/UR", "4c"]);
+        cube.apply_alg(&solution.moves());
+        assert_eq!(cube, Cube::default());
+        assert_eq!(solution.stm(), Metric::Stm.count(&solution.moves()));
+    }
+
+    #[test]
+    fn check_off_centres() {
+        for turn in ["x", "y", "z'", "M' x2", "S"] {
+            let mut cube = Cube::from(SCRAMBLE);
+            cube.apply_alg(&parse_alg(turn).unwrap());
+            let solution = solve(&cube).expect(turn);
+            cube.apply_alg(&solution.moves());
+            assert_eq!(cube, Cube::default(), "{}", turn);
+        }
+    }
+
+    #[test]
+    fn check_first_block() {
+        let cube = Cube::from(SCRAMBLE);
+        let mut solved = cube.clone();
+        solved.apply_alg(&first_block(&cube).unwrap());
+        assert_eq!(pieces(&solved, &FIRST_BLOCK_EDGES, &FIRST_BLOCK_CORNERS),
+            Some(homes(&FIRST_BLOCK_EDGES, &FIRST_BLOCK_CORNERS)));
+    }
+
+    #[test]
+    fn check_cmll_algs_keep_blocks() {
+        let blocks = |cube: &Cube| pieces(cube,
+            &[&FIRST_BLOCK_EDGES[..], &SQUARE_EDGES, &PAIR_EDGES].concat(),
+            &[&FIRST_BLOCK_CORNERS[..], &SQUARE_CORNERS, &PAIR_CORNERS].concat());
+        for (_, alg) in CMLL_ALGS {
+            let mut cube = Cube::default();
+            cube.apply_alg(&parse_alg(alg).unwrap());
+            assert_eq!(blocks(&cube), blocks(&Cube::default()), "{}", alg);
+        }
+    }
+
+    #[test]
+    fn check_cmll_case() {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg("R U2 R' U' R U' R'").unwrap());
+        let (case, _) = cmll(&cube).unwrap();
+        assert_eq!(case, "S Left Bar");
+        cube.apply_alg(&parse_alg("F R U' R' U' R U R' F' R U R' U' R' F R F'").unwrap());
+        let (case, _) = cmll(&cube).unwrap();
+        assert_eq!(case, "S Right Bar");
+        for (name, alg) in CMLL_ALGS {
+            let mut cube = Cube::default();
+            cube.apply_alg(&invert(&parse_alg(alg).unwrap()));
+            let (case, moves) = cmll(&cube).unwrap();
+            assert_eq!(case, name);
+            cube.apply_alg(&moves);
+            assert_eq!(pieces(&cube, &[], &TOP_CORNERS), Some(homes(&[], &TOP_CORNERS)));
+        }
+        assert_eq!(cmll(&Cube::default()).unwrap(), ("skip".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,410 @@
+use std::io;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::cross::shortest_cross;
+use crate::cubie::{self, CubieCube};
+use crate::facelet::*;
+use crate::notation::{Layer::{self, *}, Move, invert, simplify};
+use crate::random::{ChaCha, Random, SplitMix};
+use crate::search::is_redundant;
+use crate::tables::cached;
+use crate::thistlethwaite;
+
+fn face_moves() -> Vec<Move> {
+    Move::all(&[U, D, L, R, F, B])
+}
+
+// Face turns at random, none on the layer or the axis of the turn before
+// in a way that is found shorter elsewhere
+pub fn random_moves<R: Random>(random: &mut R, length: usize) -> Vec<Move> {
+    random_turns(random, &[U, D, L, R, F, B], length)
+}
+
+// Same as random_moves, on the given layers only
+pub fn random_turns<R: Random>(random: &mut R, layers: &[Layer], length: usize) -> Vec<Move> {
+    let moves = Move::all(layers);
+    let mut scramble: Vec<Move> = Vec::new();
+    while scramble.len() < length {
+        let m = moves[random.below(moves.len() as u64) as usize];
+        if !is_redundant(scramble.last().copied(), m) {
+            scramble.push(m);
+        }
+    }
+    scramble
+}
+
+fn shuffle<R: Random>(random: &mut R, items: &mut [u8]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random.below(i as u64 + 1) as usize);
+    }
+}
+
+fn random_cubie<R: Random>(random: &mut R) -> CubieCube {
+    let mut cubie = CubieCube::default();
+    shuffle(random, &mut cubie.cp);
+    shuffle(random, &mut cubie.ep);
+    if cubie.corner_parity() != cubie.edge_parity() {
+        cubie.ep.swap(0, 1);
+    }
+    // the last twist and flip make the sums come out right
+    for i in 0..7 {
+        cubie.co[i] = random.below(3) as u8;
+    }
+    cubie.co[7] = (3 - cubie.co[..7].iter().sum::<u8>() % 3) % 3;
+    for i in 0..11 {
+        cubie.eo[i] = random.below(2) as u8;
+    }
+    cubie.eo[11] = cubie.eo[..11].iter().sum::<u8>() % 2;
+    cubie
+}
+
+// Moves to a state drawn uniformly from all the solvable ones, the
+// inverted Thistlethwaite solution of it
+pub fn random_state<R: Random>(random: &mut R) -> Vec<Move> {
+    loop {
+        let cube = random_cubie(random).to_cube();
+        if let Some(solution) = thistlethwaite::solve(&cube) {
+            return simplify(&invert(&solution.moves()));
+        }
+    }
+}
+
+// 2x2 states are the corners alone. R, U and F leave DBL where it is, so
+// a state is the order of the other seven and the twists of six of them,
+// the last twist follows.
+const TWO_BY_TWO_SLOTS: [usize; 7] = [0, 1, 2, 3, 4, 5, 7];
+const ORDERS: usize = 5040;
+const TWISTS: usize = 729;
+
+// The WCA throws away 2x2 states closer to solved than this
+pub const TWO_BY_TWO_MIN_DISTANCE: u8 = 4;
+
+fn two_by_two_moves() -> Vec<Move> {
+    Move::all(&[R, U, F])
+}
+
+// DRB stands in for DBL among the pieces, so they count 0 to 6
+fn order_of(cubie: &CubieCube) -> usize {
+    let pieces = TWO_BY_TWO_SLOTS.map(|slot| cubie.cp[slot].min(6));
+    (0..7).fold(0, |index, i| {
+        index * (7 - i) + pieces[i + 1..].iter().filter(|&&p| p < pieces[i]).count()
+    })
+}
+
+fn twist_of(cubie: &CubieCube) -> usize {
+    TWO_BY_TWO_SLOTS[..6].iter().rev().fold(0, |index, &slot| index * 3 + cubie.co[slot] as usize)
+}
+
+fn two_by_two(order: usize, twist: usize) -> CubieCube {
+    let mut cubie = CubieCube::default();
+    let mut left: Vec<u8> = (0..7).collect();
+    let mut index = order;
+    for (i, &slot) in TWO_BY_TWO_SLOTS.iter().enumerate() {
+        let radix: usize = (1..7 - i).product();
+        let piece = left.remove(index / radix);
+        index %= radix;
+        cubie.cp[slot] = if piece == 6 { 7 } else { piece };
+    }
+    for (i, &slot) in TWO_BY_TWO_SLOTS[..6].iter().enumerate() {
+        cubie.co[slot] = (twist / 3usize.pow(i as u32) % 3) as u8;
+    }
+    cubie.co[7] = (3 - cubie.co.iter().map(|&t| t as u32).sum::<u32>() % 3) as u8 % 3;
+    cubie
+}
+
+struct TwoByTwo {
+    orders: Vec<Vec<usize>>,
+    twists: Vec<Vec<usize>>,
+    // by order * TWISTS + twist
+    distances: Vec<u8>
+}
+
+impl TwoByTwo {
+    fn next(&self, state: usize, m: usize) -> usize {
+        self.orders[state / TWISTS][m] * TWISTS + self.twists[state % TWISTS][m]
+    }
+}
+
+fn two_by_two_tables() -> &'static TwoByTwo {
+    static TABLES: OnceLock<TwoByTwo> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let moves = two_by_two_moves();
+        let after = |cubie: CubieCube| -> Vec<CubieCube> {
+            moves.iter().map(|&m| cubie.multiply(CubieCube::of_move(m))).collect()
+        };
+        let orders: Vec<Vec<usize>> = (0..ORDERS)
+            .map(|order| after(two_by_two(order, 0)).iter().map(order_of).collect())
+            .collect();
+        let twists: Vec<Vec<usize>> = (0..TWISTS)
+            .map(|twist| after(two_by_two(0, twist)).iter().map(twist_of).collect())
+            .collect();
+        let mut tables = TwoByTwo { orders, twists, distances: Vec::new() };
+        tables.distances = cached("two_by_two", || {
+            let mut distances = vec![u8::MAX; ORDERS * TWISTS];
+            distances[0] = 0;
+            let mut depth = 0;
+            let mut found = true;
+            while found {
+                found = false;
+                for state in 0..distances.len() {
+                    if distances[state] != depth {
+                        continue;
+                    }
+                    for m in 0..moves.len() {
+                        let next = tables.next(state, m);
+                        if distances[next] == u8::MAX {
+                            distances[next] = depth + 1;
+                            found = true;
+                        }
+                    }
+                }
+                depth += 1;
+            }
+            distances
+        });
+        tables
+    })
+}
+
+// Moves to a 2x2 state drawn uniformly from those at least
+// TWO_BY_TWO_MIN_DISTANCE from solved, the inverted optimal solution of it
+pub fn random_state_222<R: Random>(random: &mut R) -> Vec<Move> {
+    let tables = two_by_two_tables();
+    let moves = two_by_two_moves();
+    let mut state = loop {
+        let state = random.below((ORDERS * TWISTS) as u64) as usize;
+        if tables.distances[state] >= TWO_BY_TWO_MIN_DISTANCE {
+            break state;
+        }
+    };
+    let mut solution = Vec::new();
+    while tables.distances[state] > 0 {
+        let m = (0..moves.len())
+            .find(|&m| tables.distances[tables.next(state, m)] < tables.distances[state])
+            .unwrap();
+        solution.push(moves[m]);
+        state = tables.next(state, m);
+    }
+    invert(&solution)
+}
+
+// A reason to throw a scramble away
+pub struct Rule {
+    pub name: String,
+    rejects: Box<dyn Fn(&Cube) -> bool + Send + Sync>
+}
+
+impl Rule {
+    pub fn new(name: &str, rejects: impl Fn(&Cube) -> bool + Send + Sync + 'static) -> Self {
+        Rule { name: name.to_string(), rejects: Box::new(rejects) }
+    }
+    pub fn rejects(&self, cube: &Cube) -> bool {
+        (self.rejects)(cube)
+    }
+    pub fn or(self, other: Rule) -> Rule {
+        let name = format!("{} or {}", self.name, other.name);
+        Rule::new(&name, move |cube| self.rejects(cube) || other.rejects(cube))
+    }
+    pub fn and(self, other: Rule) -> Rule {
+        let name = format!("{} and {}", self.name, other.name);
+        Rule::new(&name, move |cube| self.rejects(cube) && other.rejects(cube))
+    }
+    pub fn negate(self) -> Rule {
+        let name = format!("not {}", self.name);
+        Rule::new(&name, move |cube| !self.rejects(cube))
+    }
+}
+
+// Some colour has its cross in `moves` or fewer
+pub fn cross_within(moves: usize) -> Rule {
+    Rule::new(&format!("cross in {}", moves), move |cube| shortest_cross(cube, moves).is_some())
+}
+
+// Some corner is home next to an edge that is home, for any cross colour
+pub fn pair_solved() -> Rule {
+    Rule::new("pair solved", |cube| {
+        let Some(cubie) = CubieCube::from_cube(cube) else {
+            return false;
+        };
+        let home = |c: usize| cubie.cp[c] as usize == c && cubie.co[c] == 0;
+        let edge_home = |e: usize| cubie.ep[e] as usize == e && cubie.eo[e] == 0;
+        (0..8).filter(|&c| home(c)).any(|c| {
+            let faces = CORNER_FACELETS[c].map(face_of);
+            (0..12).any(|e| edge_home(e) && EDGE_FACELETS[e].iter().all(|&f| faces.contains(&face_of(f))))
+        })
+    })
+}
+
+// Solvable in `moves` face turns or fewer
+pub fn within(moves: usize) -> Rule {
+    let turns = face_moves();
+    Rule::new(&format!("within {}", moves), move |cube| {
+        CubieCube::from_cube(cube).is_some_and(|start| {
+            let solved = |c: &CubieCube| *c == CubieCube::default();
+            cubie::search(&start, &turns, &cubie::distance_bound, &solved, moves).is_some()
+        })
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scrambler {
+    // random face turns, this many of them
+    Moves(usize),
+    State
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterStats {
+    pub generated: usize,
+    pub accepted: usize,
+    // by rule, in the order they were added. A scramble counts for every
+    // rule that throws it away.
+    pub rejected: Vec<(String, usize)>
+}
+
+// Draws for one scramble, before giving up on the rules
+pub const MAX_ATTEMPTS: usize = 10_000;
+
+pub struct Filtered<R: Random = SplitMix> {
+    scrambler: Scrambler,
+    rules: Vec<Rule>,
+    attempts: usize,
+    stats: FilterStats,
+    random: R
+}
+
+impl Filtered {
+    pub fn new(scrambler: Scrambler) -> Self {
+        Filtered {
+            scrambler,
+            rules: Vec::new(),
+            attempts: MAX_ATTEMPTS,
+            stats: FilterStats::default(),
+            random: SplitMix::from_time()
+        }
+    }
+    // For official scrambles, that nobody can work out in advance
+    pub fn secure(scrambler: Scrambler) -> io::Result<Filtered<ChaCha>> {
+        Ok(Filtered::new(scrambler).with_random(ChaCha::from_entropy()?))
+    }
+}
+
+impl<R: Random> Filtered<R> {
+    pub fn with_random<S: Random>(self, random: S) -> Filtered<S> {
+        Filtered { scrambler: self.scrambler, rules: self.rules, attempts: self.attempts, stats: self.stats, random }
+    }
+    pub fn with_attempts(self, attempts: usize) -> Self {
+        Filtered { attempts, ..self }
+    }
+    pub fn reject(mut self, rule: Rule) -> Self {
+        self.stats.rejected.push((rule.name.clone(), 0));
+        self.rules.push(rule);
+        self
+    }
+    pub fn stats(&self) -> &FilterStats {
+        &self.stats
+    }
+    // Draws until a scramble passes every rule, None when none of the
+    // attempts does. The stats count the failed draws as well.
+    pub fn next_scramble(&mut self) -> Option<Vec<Move>> {
+        for _ in 0..self.attempts {
+            let scramble = match self.scrambler {
+                Scrambler::Moves(length) => random_moves(&mut self.random, length),
+                Scrambler::State => random_state(&mut self.random)
+            };
+            let mut cube = Cube::default();
+            cube.apply_alg(&scramble);
+            self.stats.generated += 1;
+            let mut passed = true;
+            for (rule, (_, count)) in self.rules.iter().zip(&mut self.stats.rejected) {
+                if rule.rejects(&cube) {
+                    *count += 1;
+                    passed = false;
+                }
+            }
+            if passed {
+                self.stats.accepted += 1;
+                return Some(scramble);
+            }
+        }
+        None
+    }
+    pub fn scrambles(&mut self, count: usize) -> Option<Vec<Vec<Move>>> {
+        (0..count).map(|_| self.next_scramble()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_rules() {
+        let solved = Cube::default();
+        assert!(cross_within(2).rejects(&solved) && pair_solved().rejects(&solved) && within(0).rejects(&solved));
+        let cube = Cube::from("R U R' F2 D");
+        assert!(cross_within(2).rejects(&cube) && !cross_within(1).rejects(&cube));
+        assert!(within(5).rejects(&cube) && !within(4).rejects(&cube));
+        let sune = Cube::from("R U R' U R U2 R'");
+        assert!(within(7).rejects(&sune) && !within(6).rejects(&sune));
+        assert!(!pair_solved().rejects(&Cube::from("R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F")));
+        assert!(pair_solved().rejects(&Cube::from("R U")));
+        let both = within(4).or(cross_within(1));
+        assert_eq!(both.name, "within 4 or cross in 1");
+        assert!(!both.rejects(&cube) && both.negate().rejects(&cube));
+        assert!(!within(5).and(cross_within(1)).rejects(&cube));
+    }
+
+    #[test]
+    fn check_two_by_two() {
+        let cubie = CubieCube::from_cube(&Cube::from("R U F' R2 U'")).unwrap();
+        assert_eq!(two_by_two(order_of(&cubie), twist_of(&cubie)).cp, cubie.cp);
+        assert_eq!(two_by_two(order_of(&cubie), twist_of(&cubie)).co, cubie.co);
+        let mut counts = [0; 12];
+        for &distance in &two_by_two_tables().distances {
+            counts[distance as usize] += 1;
+        }
+        assert_eq!(counts, [1, 9, 54, 321, 1847, 9992, 50136, 227536, 870072, 1887748, 623800, 2644]);
+        let mut random = SplitMix::new(5);
+        for _ in 0..20 {
+            let scramble = random_state_222(&mut random);
+            assert!((4..=11).contains(&scramble.len()));
+            let mut cubie = CubieCube::default();
+            cubie.apply_alg(&scramble);
+            let state = order_of(&cubie) * TWISTS + twist_of(&cubie);
+            assert_eq!(two_by_two_tables().distances[state] as usize, scramble.len());
+        }
+    }
+
+    #[test]
+    fn check_filtered() {
+        let mut filtered = Filtered::new(Scrambler::Moves(4))
+            .with_random(SplitMix::new(3))
+            .reject(within(3))
+            .reject(pair_solved());
+        let scrambles = filtered.scrambles(5).unwrap();
+        for scramble in &scrambles {
+            let mut cube = Cube::default();
+            cube.apply_alg(scramble);
+            assert!(!within(3).rejects(&cube) && !pair_solved().rejects(&cube));
+        }
+        let stats = filtered.stats();
+        assert_eq!(stats.accepted, 5);
+        assert_eq!(stats.rejected[1].0, "pair solved");
+        assert!(stats.rejected[1].1 > 0);
+        assert!(stats.generated >= 5 + stats.rejected[1].1);
+        let mut secure = Filtered::secure(Scrambler::State).unwrap().reject(within(2));
+        let mut cube = Cube::default();
+        cube.apply_alg(&secure.next_scramble().unwrap());
+        assert!(CubieCube::from_cube(&cube).unwrap().is_solvable());
+    }
+
+    #[test]
+    fn check_attempts() {
+        let mut hopeless = Filtered::new(Scrambler::Moves(2)).reject(within(3)).with_attempts(50);
+        assert_eq!(hopeless.next_scramble(), None);
+        let stats = hopeless.stats();
+        assert_eq!((stats.generated, stats.accepted, stats.rejected[0].1), (50, 0, 50));
+        assert_eq!(hopeless.scrambles(2), None);
+    }
+}
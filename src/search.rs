@@ -0,0 +1,198 @@
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use crate::facelet::apply_to_stickers;
+use crate::notation::Move;
+use crate::tables::Table;
+
+// Searches run on the stickers of the pieces they care about, every
+// piece is tracked by the position of its reference sticker
+
+pub(crate) fn key(stickers: &[u8]) -> u128 {
+    stickers.iter().fold(0, |key, &s| key << 7 | s as u128)
+}
+
+// Same as key, but for pieces that can not be told apart
+pub(crate) fn shape(stickers: &[u8]) -> u128 {
+    stickers.iter().fold(0, |key, &s| key | 1 << s)
+}
+
+// Skips sequences like R R' or L R L, that are found shorter elsewhere
+pub(crate) fn is_redundant(last: Option<Move>, next: Move) -> bool {
+    match last {
+        Some(last) => last.layer == next.layer
+            || (last.layer.axis() == next.layer.axis() && last.layer > next.layer),
+        None => false
+    }
+}
+
+// Distances to the goal for a part of the tracked stickers
+pub(crate) struct Pruning {
+    pieces: Vec<usize>,
+    identical: bool,
+    distances: HashMap<u128, u8>,
+    depth: u8
+}
+
+impl Pruning {
+    pub(crate) fn new(pieces: Vec<usize>, goal: &[u8], moves: &[Move], depth: u8) -> Self {
+        Pruning::build(pieces, false, &[goal.to_vec()], moves, depth)
+    }
+    // Only tells where the pieces are as a whole, like edge orientation
+    pub(crate) fn identical(pieces: Vec<usize>, goal: &[u8], moves: &[Move], depth: u8) -> Self {
+        Pruning::build(pieces, true, &[goal.to_vec()], moves, depth)
+    }
+    // For steps, that end in any of several states
+    pub(crate) fn any_of(pieces: Vec<usize>, goals: &[Vec<u8>], moves: &[Move], depth: u8) -> Self {
+        Pruning::build(pieces, false, goals, moves, depth)
+    }
+    fn key(&self, stickers: &[u8]) -> u128 {
+        if self.identical { shape(stickers) } else { key(stickers) }
+    }
+    fn build(pieces: Vec<usize>, identical: bool, goals: &[Vec<u8>], moves: &[Move], depth: u8) -> Self {
+        let mut table = Pruning { pieces, identical, distances: HashMap::new(), depth };
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        for goal in goals {
+            let start: Vec<u8> = table.pieces.iter().map(|&i| goal[i]).collect();
+            if distances.insert(table.key(&start), 0).is_none() {
+                queue.push_back((start, 0));
+            }
+        }
+        while let Some((stickers, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            for &m in moves {
+                let mut next = stickers.clone();
+                apply_to_stickers(&mut next, m);
+                distances.entry(table.key(&next)).or_insert_with(|| {
+                    queue.push_back((next, distance + 1));
+                    distance + 1
+                });
+            }
+        }
+        table.distances = distances;
+        table
+    }
+    pub(crate) fn bound(&self, stickers: &[u8]) -> u8 {
+        let part: Vec<u8> = self.pieces.iter().map(|&i| stickers[i]).collect();
+        self.distances.get(&self.key(&part)).copied().unwrap_or(self.depth + 1)
+    }
+}
+
+impl Table for Pruning {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.pieces.write(out);
+        self.identical.write(out);
+        self.distances.write(out);
+        self.depth.write(out);
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        Some(Pruning {
+            pieces: Table::read(input)?,
+            identical: Table::read(input)?,
+            distances: Table::read(input)?,
+            depth: Table::read(input)?
+        })
+    }
+}
+
+pub(crate) type Goal<'a> = &'a dyn Fn(&[u8]) -> bool;
+
+pub(crate) struct Search<'a> {
+    pub moves: &'a [Move],
+    pub tables: &'a [Pruning],
+    pub goal: Goal<'a>
+}
+
+impl Search<'_> {
+    fn bound(&self, stickers: &[u8]) -> u8 {
+        self.tables.iter().map(|t| t.bound(stickers)).max().unwrap_or(0)
+    }
+    // Iterative deepening, `found` gets every solution of the current
+    // depth and returns true to stop the search
+    pub(crate) fn run(
+        &self,
+        start: &[u8],
+        max_depth: usize,
+        found: &mut dyn FnMut(&[Move]) -> bool
+    ) {
+        let mut path = Vec::new();
+        for depth in 0..=max_depth {
+            if self.step(start, depth, &mut path, found) {
+                return;
+            }
+        }
+    }
+    pub(crate) fn first(&self, start: &[u8], max_depth: usize) -> Option<Vec<Move>> {
+        let mut result = None;
+        self.run(start, max_depth, &mut |path| {
+            result = Some(path.to_vec());
+            true
+        });
+        result
+    }
+    fn step(
+        &self,
+        stickers: &[u8],
+        depth: usize,
+        path: &mut Vec<Move>,
+        found: &mut dyn FnMut(&[Move]) -> bool
+    ) -> bool {
+        if depth == 0 {
+            return (self.goal)(stickers) && found(path);
+        }
+        if self.bound(stickers) as usize > depth {
+            return false;
+        }
+        for &m in self.moves {
+            if is_redundant(path.last().copied(), m) {
+                continue;
+            }
+            let mut next = stickers.to_vec();
+            apply_to_stickers(&mut next, m);
+            path.push(m);
+            let stop = self.step(&next, depth - 1, path, found);
+            path.pop();
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// Plain breadth first search, for steps with few enough states
+pub(crate) fn breadth_first(
+    start: &[u8],
+    moves: &[Move],
+    goal: Goal,
+    max_depth: usize
+) -> Option<Vec<Move>> {
+    let mut parents: HashMap<u128, Option<(u128, Move)>> = HashMap::from([(key(start), None)]);
+    let mut queue = VecDeque::from([(start.to_vec(), 0)]);
+    while let Some((stickers, depth)) = queue.pop_front() {
+        if goal(&stickers) {
+            let mut path = Vec::new();
+            let mut current = key(&stickers);
+            while let Some((parent, m)) = parents[&current] {
+                path.push(m);
+                current = parent;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if depth == max_depth {
+            continue;
+        }
+        for &m in moves {
+            let mut next = stickers.clone();
+            apply_to_stickers(&mut next, m);
+            if let Entry::Vacant(entry) = parents.entry(key(&next)) {
+                entry.insert(Some((key(&stickers), m)));
+                queue.push_back((next, depth + 1));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::cubie::CubieCube;
+use crate::facelet::Facelet;
+use crate::last_layer::{OLL_ALGS, PLL_ALGS, is_f2l_solved};
+use crate::roux::CMLL_ALGS;
+use crate::notation::{Layer::U, Move, invert, parse_alg};
+
+// Last layer subsets, each described by what of the last layer it reads.
+// Sets are made from (name, algorithm) lists with `CaseSet::from_algs`,
+// the built in ones take the usual names:
+//
+// CMLL     the shape of the corners and what their side stickers show
+// COLL     the same corner cases, numbered within the shape in the order
+//          of the CMLL names
+// ZBLL     the COLL case, then the EPLL left once the corners are solved
+// OLLCP    the OLL number, then the PLL that swaps the corners left after
+//          the OLL alg, T for two of them and Y for a diagonal
+// WV       the OCLL, that R U R' would leave
+//
+// A case seen from the side is named like "Ua from R", the usual alg is
+// done after a y rotation then.
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Read {
+    // the face of the sticker colour
+    Colour,
+    // only whether the sticker has the top colour
+    Top
+}
+
+// What colour belongs to what face
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Scheme {
+    // the centres, with the first two layers solved
+    Centres,
+    // the Roux blocks, the M slice may be anywhere
+    Blocks
+}
+
+const MASKED: u8 = u8::MAX;
+
+pub const CORNER_READS: [(Facelet, Read); 12] = [
+    ((0, 3), Read::Colour), ((0, 5), Read::Colour), ((2, 3), Read::Colour), ((2, 5), Read::Colour),
+    ((3, 0), Read::Colour), ((3, 2), Read::Colour), ((3, 3), Read::Colour), ((3, 5), Read::Colour),
+    ((3, 6), Read::Colour), ((3, 8), Read::Colour), ((3, 9), Read::Colour), ((3, 11), Read::Colour)
+];
+
+pub const EDGE_ORIENTATION_READS: [(Facelet, Read); 4] = [
+    ((0, 4), Read::Top), ((1, 3), Read::Top), ((1, 5), Read::Top), ((2, 4), Read::Top)
+];
+
+pub const EDGE_READS: [(Facelet, Read); 8] = [
+    ((0, 4), Read::Colour), ((1, 3), Read::Colour), ((1, 5), Read::Colour), ((2, 4), Read::Colour),
+    ((3, 1), Read::Colour), ((3, 4), Read::Colour), ((3, 7), Read::Colour), ((3, 10), Read::Colour)
+];
+
+const CORNER_ORIENTATION_READS: [(Facelet, Read); 12] = {
+    let mut reads = CORNER_READS;
+    let mut i = 0;
+    while i < reads.len() {
+        reads[i].1 = Read::Top;
+        i += 1;
+    }
+    reads
+};
+
+fn block_scheme(cube: &Cube) -> Option<[Stiker; 6]> {
+    let [left, front, right, back, down] = [(4, 1), (5, 3), (4, 7), (5, 11), (7, 3)].map(|f| cube.color(f));
+    let sides = [left, front, right, back, down];
+    let up = Cube::default().centre_colors().into_iter().find(|c| !sides.contains(c))?;
+    let scheme = [up, left, front, right, back, down];
+    let blocks = [
+        (4..6, 0..3), (4..6, 3..4), (4..6, 5..6), (4..6, 6..9), (4..6, 9..10), (4..6, 11..12),
+        (6..9, 3..4), (6..9, 5..6)
+    ];
+    let solved = blocks.into_iter().all(|(rows, cols)| rows.into_iter().all(|row| {
+        cols.clone().all(|col| cube.color((row, col)) == scheme[crate::facelet::face_of((row, col))])
+    }));
+    solved.then_some(scheme)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsetCase {
+    pub set: &'static str,
+    pub name: String,
+    pub auf: Move,
+    pub post_auf: Move
+}
+
+pub struct CaseSet {
+    pub name: &'static str,
+    reads: Vec<(Facelet, Read)>,
+    scheme: Scheme,
+    // done before reading, like the insertion of Winter Variation
+    before: Vec<Move>,
+    auf: bool,
+    names: Vec<String>,
+    states: HashMap<Vec<u8>, (usize, u8, u8)>
+}
+
+fn u_turn(amount: u8) -> &'static CubieCube {
+    CubieCube::of_move(Move::new(U, amount))
+}
+
+impl CaseSet {
+    fn empty(name: &'static str, reads: &[(Facelet, Read)], scheme: Scheme, before: &str, auf: bool) -> Self {
+        CaseSet {
+            name,
+            reads: reads.to_vec(),
+            scheme,
+            before: parse_alg(before).unwrap(),
+            auf,
+            names: Vec::new(),
+            states: HashMap::new()
+        }
+    }
+    fn read(&self, cube: &Cube, scheme: &[Stiker; 6]) -> Option<Vec<u8>> {
+        self.reads.iter().map(|&(f, read)| {
+            let color = cube.color(f);
+            if color == Stiker::V {
+                return Some(MASKED);
+            }
+            let face = scheme.iter().position(|&c| c == color)? as u8;
+            Some(match read {
+                Read::Colour => face,
+                Read::Top => (face == 0) as u8
+            })
+        }).collect()
+    }
+    // Adds the case of the state with all of its AUFs, unless it is there
+    fn add(&mut self, state: &CubieCube, name: impl FnOnce() -> String) {
+        let scheme = Cube::default().centre_colors();
+        if self.states.contains_key(&self.read(&state.to_cube(), &scheme).unwrap()) {
+            return;
+        }
+        let case = self.names.len();
+        self.names.push(name());
+        let turns = if self.auf { 4 } else { 1 };
+        for pre in 0..turns {
+            for post in 0..turns {
+                let variant = u_turn(4 - post).multiply(state).multiply(u_turn(4 - pre));
+                let key = self.read(&variant.to_cube(), &scheme).unwrap();
+                self.states.entry(key).or_insert((case, pre, post));
+            }
+        }
+    }
+    pub fn from_algs(
+        name: &'static str,
+        reads: &[(Facelet, Read)],
+        scheme: Scheme,
+        auf: bool,
+        cases: &[(&str, &str)]
+    ) -> Option<Self> {
+        let mut set = CaseSet::empty(name, reads, scheme, "", auf);
+        for (case, alg) in cases {
+            set.add_alg(&parse_alg(alg)?, || case.to_string())?;
+        }
+        Some(set)
+    }
+    // The case, that the algorithm solves. It is undone on the stickers,
+    // as the cubies do not follow wide turns.
+    fn add_alg(&mut self, alg: &[Move], name: impl FnOnce() -> String) -> Option<()> {
+        let mut cube = Cube::default();
+        cube.apply_alg(&invert(alg));
+        self.add(&CubieCube::from_cube(&cube)?, name);
+        Some(())
+    }
+    pub fn cases(&self) -> &[String] {
+        &self.names
+    }
+    // Stickers, that are V in the scan, match any colour, as long as only
+    // one case fits
+    pub fn recognise(&self, cube: &Cube) -> Option<SubsetCase> {
+        let mut cube = cube.clone();
+        cube.apply_alg(&self.before);
+        let scheme = match self.scheme {
+            Scheme::Centres => is_f2l_solved(&cube).then(|| cube.centre_colors())?,
+            Scheme::Blocks => block_scheme(&cube)?
+        };
+        let state = self.read(&cube, &scheme)?;
+        let (case, pre, post) = match self.states.get(&state) {
+            Some(&found) => found,
+            None => {
+                let fits = |key: &Vec<u8>| key.iter().zip(&state).all(|(&k, &s)| s == MASKED || k == s);
+                let mut found = self.states.iter().filter(|(key, _)| fits(key)).map(|(_, &found)| found);
+                let first = found.next()?;
+                if found.any(|other| other.0 != first.0) {
+                    return None;
+                }
+                first
+            }
+        };
+        Some(SubsetCase {
+            set: self.name,
+            name: self.names[case].clone(),
+            auf: Move::new(U, pre),
+            post_auf: Move::new(U, post)
+        })
+    }
+}
+
+pub const COLL_ALGS: [(&str, &str); 42] = [
+    ("O Adjacent", "R U R' F' R U R' U' R' F R2 U' R'"),
+    ("O Diagonal", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("H 1", "R U2 R' U' R U R' U' R U' R'"),
+    ("H 2", "F R U R' U' R U R' U' R U R' U' F'"),
+    ("H 3", "R U2 R2 F U' R2 U' R2 U F' U R"),
+    ("H 4", "R' F' R U2 R U2 R' F U' R U' R'"),
+    ("Pi 1", "R U2 R2 U' R2 U' R2 U2 R"),
+    ("Pi 2", "R' F2 R U2 R U2 R' F2 U' R U' R'"),
+    ("Pi 3", "R F U R2 U2 R2 U R2 U R2 F' R'"),
+    ("Pi 4", "R U R' U F2 R U2 R' U2 R' F2 R"),
+    ("Pi 5", "F' U' F U' R F' U2 F U2 F R' F'"),
+    ("Pi 6", "R F' U' R2 F U' F' U R2 U F R'"),
+    ("U 1", "R2 D R' U2 R D' R' U2 R'"),
+    ("U 2", "R2 D' R U2 R' D R U2 R"),
+    ("U 3", "R' U2 R U R' U R U R' U' R U' R' U2 R"),
+    ("U 4", "R' F R U' R' U' R U R' F' R U R' U' R' F R F' R"),
+    ("U 5", "F U2 F' R F R' U2 R F' R'"),
+    ("U 6", "F U2 F' R' U F U F' U' R"),
+    ("T 1", "F U F' R' F U' F' U R"),
+    ("T 2", "R' U' R F R' U R U' F'"),
+    ("T 3", "r U R' U' r' F R F' U x R' U R' D2 R U' R' D2 R2 x'"),
+    ("T 4", "R' U R U2 L' R' U R U' L"),
+    ("T 5", "R F R' U2 R F' R' F U2 F'"),
+    ("T 6", "R' U F' R' U2 R U2 F U' R' U R2"),
+    ("L 1", "F U R' U' R F' R' U R"),
+    ("L 2", "R' U' F U F' R F U' F'"),
+    ("L 3", "R U2 R' U' R U R' U' R U R' U' R U' R'"),
+    ("L 4", "R U2 R D R' U2 R D' R2"),
+    ("L 5", "R' U' R U R' F' R U R' U' R' F R2"),
+    ("L 6", "R' U2 R' D' R U2 R' D R2"),
+    ("S 1", "R U R' U R U2 R'"),
+    ("S 2", "R' U2 R U F R' U R U' F'"),
+    ("S 3", "R' U' F U F' R U F U2 F'"),
+    ("S 4", "R2 D' R U' R' D R U' R U R' U R"),
+    ("S 5", "R2 U F U F' U' R U2 F U2 F' R"),
+    ("S 6", "R U' L' U R' U' L"),
+    ("AS 1", "R' U' R U' R' U2 R"),
+    ("AS 2", "R2 D R' U R D' R' U R' U' R U' R'"),
+    ("AS 3", "F U R' U' R F' U' R' U2 R"),
+    ("AS 4", "F U2 F' U' R' F U' F' U R"),
+    ("AS 5", "L' U R U' L U R'"),
+    ("AS 6", "R' F U2 F' U2 R' U F U' F' U' R2")
+];
+
+pub const OCLL_ALGS: [(&str, &str); 7] = [
+    ("Sune", "R U R' U R U2 R'"),
+    ("Antisune", "R U2 R' U' R U' R'"),
+    ("H", "R U2 R' U' R U R' U' R U' R'"),
+    ("Pi", "R U2 R2 U' R2 U' R2 U2 R"),
+    ("Headlights", "R2 D' R U2 R' D R U2 R"),
+    ("Chameleon", "r U R' U' r' F R F'"),
+    ("Bowtie", "F' r U R' U' r' F R")
+];
+
+pub fn coll() -> &'static CaseSet {
+    static SET: OnceLock<CaseSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        let reads = [&CORNER_READS[..], &EDGE_ORIENTATION_READS].concat();
+        let cases = [&[("skip", "")][..], &COLL_ALGS].concat();
+        CaseSet::from_algs("COLL", &reads, Scheme::Centres, true, &cases).unwrap()
+    })
+}
+
+const ANGLES: [(&str, &str); 4] = [("", ""), (" from R", "y"), (" from B", "y2"), (" from L", "y'")];
+
+fn from_side(alg: &str, rotation: &str) -> Vec<Move> {
+    let rotation = parse_alg(rotation).unwrap();
+    [rotation.clone(), parse_alg(alg).unwrap(), invert(&rotation)].concat()
+}
+
+// The PLLs of the given names from all four sides, after no PLL at all
+fn plls(names: &[&str]) -> Vec<(String, Vec<Move>)> {
+    let mut result = vec![(String::new(), vec![])];
+    for (name, alg) in PLL_ALGS.iter().filter(|(name, _)| names.contains(name)) {
+        for (side, rotation) in ANGLES {
+            result.push((format!(" {}{}", name, side), from_side(alg, rotation)));
+        }
+    }
+    result
+}
+
+// ZBLL leaves out the cases with oriented corners, those are PLLs
+pub fn zbll() -> &'static CaseSet {
+    static SET: OnceLock<CaseSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        let reads = [&CORNER_READS[..], &EDGE_READS].concat();
+        let mut set = CaseSet::empty("ZBLL", &reads, Scheme::Centres, "", true);
+        let edges = plls(&["Ua", "Ub", "H", "Z"]);
+        for (case, alg) in COLL_ALGS.iter().filter(|(case, _)| !case.starts_with("O ")) {
+            for (pll, cycle) in &edges {
+                set.add_alg(&[parse_alg(alg).unwrap(), cycle.clone()].concat(), || format!("{}{}", case, pll))
+                    .unwrap();
+            }
+        }
+        set
+    })
+}
+
+pub fn ollcp() -> &'static CaseSet {
+    static SET: OnceLock<CaseSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        let reads = [&CORNER_READS[..], &EDGE_ORIENTATION_READS].concat();
+        let mut set = CaseSet::empty("OLLCP", &reads, Scheme::Centres, "", true);
+        let corners = plls(&["T", "Y"]);
+        for (number, alg) in OLL_ALGS.iter().enumerate() {
+            for (pll, swap) in &corners {
+                set.add_alg(&[parse_alg(alg).unwrap(), swap.clone()].concat(), || format!("{}{}", number + 1, pll))
+                    .unwrap();
+            }
+        }
+        set
+    })
+}
+
+pub fn cmll() -> &'static CaseSet {
+    static SET: OnceLock<CaseSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        let cases = [&[("skip", "")][..], &CMLL_ALGS].concat();
+        CaseSet::from_algs("CMLL", &CORNER_READS, Scheme::Blocks, true, &cases).unwrap()
+    })
+}
+
+// The last pair goes in with R U R', while the corners get oriented
+pub fn winter_variation() -> &'static CaseSet {
+    static SET: OnceLock<CaseSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        let reads = [&CORNER_ORIENTATION_READS[..], &EDGE_ORIENTATION_READS].concat();
+        let mut set = CaseSet::empty("WV", &reads, Scheme::Centres, "R U R'", false);
+        set.add(&CubieCube::default(), || "skip".to_string());
+        for (case, alg) in OCLL_ALGS {
+            for (side, rotation) in ANGLES {
+                set.add_alg(&from_side(alg, rotation), || format!("{}{}", case, side)).unwrap();
+            }
+        }
+        set
+    })
+}
+
+pub fn sets() -> [&'static CaseSet; 5] {
+    [coll(), zbll(), ollcp(), cmll(), winter_variation()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn case(setup: &str) -> Cube {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(setup).unwrap());
+        cube
+    }
+
+    fn counts(set: &CaseSet) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for name in set.cases() {
+            let prefix = name.split(' ').next().unwrap();
+            match counts.iter_mut().find(|(p, _)| p == prefix) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((prefix.to_string(), 1))
+            }
+        }
+        counts.sort();
+        counts
+    }
+
+    #[test]
+    fn check_sizes() {
+        let count = |set: &CaseSet, shape: &str| counts(set).iter().find(|(p, _)| p == shape).map_or(0, |c| c.1);
+        assert_eq!(zbll().cases().len(), 472);
+        assert_eq!(count(zbll(), "H"), 40);
+        for shape in ["Pi", "U", "T", "L", "S", "AS"] {
+            assert_eq!(count(zbll(), shape), 72, "{}", shape);
+        }
+        assert_eq!(coll().cases().len(), 43);
+        assert_eq!(coll().cases()[0], "skip");
+        assert_eq!(count(coll(), "H"), 4);
+        assert_eq!(count(coll(), "Pi"), 6);
+        assert_eq!(count(coll(), "O"), 2);
+        assert_eq!(cmll().cases().len(), 43);
+        assert_eq!(winter_variation().cases().len(), 27);
+        assert_eq!(ollcp().cases().len(), 329);
+        assert_eq!(counts(ollcp()).iter().find(|(p, _)| p == "27"), Some(&("27".to_string(), 6)));
+    }
+
+    #[test]
+    fn check_names() {
+        let name = |set: &CaseSet, alg: &str| {
+            let mut cube = Cube::default();
+            cube.apply_alg(&invert(&parse_alg(alg).unwrap()));
+            set.recognise(&cube).unwrap().name
+        };
+        assert_eq!(name(coll(), "F U R' U F2 U' F2 U' R F2 U2 F"), "Pi 3");
+        assert_eq!(name(coll(), "R U2 R' U' R U R' U' R U R' U' R U' R'"), "L 3");
+        assert_eq!(name(zbll(), "R U R' U R U2 R'"), "S 1");
+        assert_eq!(name(zbll(), "R U R' U R U2 R' M2 U M U2 M' U M2"), "S 1 Ua");
+        assert_eq!(name(zbll(), "R U R' U R U2 R' y M2 U' M U2 M' U' M2 y'"), "S 1 Ub from R");
+        assert_eq!(name(zbll(), "R U2 R' U' R U R' U' R U' R' M2 U M2 U2 M2 U M2"), "H 1 H");
+        assert_eq!(name(ollcp(), "R U R' U R U2 R'"), "27");
+        assert_eq!(name(ollcp(), "R U R' U R U2 R' R U R' U' R' F R2 U' R' U' R U R' F'"), "27 T");
+        assert_eq!(name(ollcp(), "R U R' U R U2 R' F R U' R' U' R U R' F' R U R' U' R' F R F'"), "27 Y");
+        assert_eq!(name(ollcp(), "F R U R' U' F'"), "45");
+        assert_eq!(name(cmll(), "R U R' U' R' F R F'"), "T Left Bar");
+        assert_eq!(name(cmll(), "F R U' R' U' R U R' F'"), "L Mirror");
+        assert_eq!(winter_variation().recognise(&case("R U2 R' U' R U' R' R U' R'")).unwrap().name, "Sune");
+        assert_eq!(winter_variation().recognise(&case("R U2 R' U' R U' R' U' R U' R'")).unwrap().name, "Sune from R");
+        assert_eq!(winter_variation().recognise(&case("R U R' U R U2 R' R U' R'")).unwrap().name, "Antisune");
+        for (set, table) in [(coll(), &COLL_ALGS), (cmll(), &CMLL_ALGS)] {
+            for (case, alg) in table {
+                assert_eq!(&name(set, alg), case, "{}", set.name);
+            }
+        }
+    }
+
+    #[test]
+    fn check_recognise() {
+        let sune = parse_alg("R U R' U R U2 R'").unwrap();
+        for set in [coll(), zbll(), ollcp()] {
+            let found: Vec<SubsetCase> = [(0, 0), (1, 3), (2, 1), (3, 2)].iter().map(|&(pre, post)| {
+                let mut cube = Cube::default();
+                cube.apply(Move::new(U, 4 - post));
+                cube.apply_alg(&invert(&sune));
+                cube.apply(Move::new(U, 4 - pre));
+                let found = set.recognise(&cube).unwrap();
+                cube.apply(found.auf);
+                assert_eq!(set.recognise(&cube).unwrap().auf, Move::new(U, 0));
+                found
+            }).collect();
+            assert!(found.iter().all(|f| f.name == found[0].name), "{}", set.name);
+        }
+        assert!(zbll().recognise(&case("R U R' U R U2 R'")).unwrap().name.starts_with("AS "));
+        assert_eq!(coll().recognise(&case("R U R' U R U2 R'")).unwrap().name, "AS 1");
+        assert_eq!(coll().recognise(&case("R")), None);
+        assert_eq!(coll().recognise(&case("F R U R' U' F'")), None);
+        let roux = case("M2 U R U2 R' U' R U' R' M'");
+        assert!(!is_f2l_solved(&roux) && cmll().recognise(&roux).is_some());
+        assert_eq!(cmll().recognise(&case("M2 U R U2 R' U' R U' R' M' R")), None);
+    }
+
+    #[test]
+    fn check_masks() {
+        let mut cube = case("R U2 R' U' R U' R' U");
+        let found = zbll().recognise(&cube).unwrap();
+        cube.scan[3][4] = Stiker::V;
+        cube.scan[0][4] = Stiker::V;
+        assert_eq!(zbll().recognise(&cube), Some(found));
+        for col in 0..12 {
+            cube.scan[3][col] = Stiker::V;
+        }
+        assert_eq!(zbll().recognise(&cube), None);
+        let set = CaseSet::from_algs("OCLL", &CORNER_ORIENTATION_READS, Scheme::Centres, true, &[
+            ("Sune", "R U R' U R U2 R'"), ("Antisune", "R U2 R' U' R U' R'")
+        ]).unwrap();
+        assert_eq!(set.recognise(&case("U R U2 R' U' R U' R'")).unwrap().name, "Sune");
+        assert_eq!(set.cases(), ["Sune", "Antisune"]);
+    }
+
+    #[test]
+    fn check_winter_variation() {
+        let set = winter_variation();
+        assert_eq!(set.recognise(&case("R U' R'")).unwrap().name, "skip");
+        let found = set.recognise(&case("R U2 R' U' R U' R' R U' R'")).unwrap();
+        assert_eq!(found.auf, Move::new(U, 0));
+        assert_ne!(found.name, set.cases()[0]);
+        assert_eq!(set.recognise(&Cube::default()), None);
+    }
+}
@@ -0,0 +1,416 @@
+use std::fmt::Write;
+use crate::{Cube, Stiker};
+use crate::cubie::CubieCube;
+use crate::facelet::{CORNER_FACELETS, EDGE_FACELETS, Facelet, face_of};
+use crate::notation::{Move, invert};
+
+// Pictures are written out by hand as SVG. The net is laid out in whole
+// pixels, the views print every point with one decimal, so the same cube
+// always gives the same text.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgStyle {
+    // in pixels
+    pub sticker: usize,
+    pub gap: usize,
+    pub border: usize,
+    pub border_color: String,
+    // fills of B, R, Y, O, G and W
+    pub scheme: [String; 6],
+    // V stickers in the views, the net leaves them out
+    pub grey: String
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle {
+            sticker: 20,
+            gap: 2,
+            border: 1,
+            border_color: "#000000".to_string(),
+            scheme: ["#0045ad", "#b90000", "#ffd500", "#ff5900", "#009b48", "#ffffff"].map(String::from),
+            grey: "#808080".to_string()
+        }
+    }
+}
+
+impl SvgStyle {
+    // None for the cells off the net
+    pub fn fill(&self, stiker: Stiker) -> Option<&str> {
+        let index = match stiker {
+            Stiker::B => 0,
+            Stiker::R => 1,
+            Stiker::Y => 2,
+            Stiker::O => 3,
+            Stiker::G => 4,
+            Stiker::W => 5,
+            Stiker::V => return None
+        };
+        Some(&self.scheme[index])
+    }
+}
+
+pub(crate) fn open(out: &mut String, width: usize, height: usize) {
+    write!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height).unwrap();
+    out.push('\n');
+}
+
+pub(crate) fn close(out: &mut String) {
+    out.push_str("</svg>\n");
+}
+
+impl Cube {
+    pub fn to_svg(&self) -> String {
+        self.to_svg_with(&SvgStyle::default())
+    }
+    // The net as the scan has it, a square a cell
+    pub fn to_svg_with(&self, style: &SvgStyle) -> String {
+        let step = style.sticker + style.gap;
+        let margin = style.border;
+        let mut out = String::new();
+        open(&mut out, 2 * margin + 12 * step - style.gap, 2 * margin + 9 * step - style.gap);
+        for (row, cells) in self.scan.iter().enumerate() {
+            for (col, &stiker) in cells.iter().enumerate() {
+                let Some(fill) = style.fill(stiker) else {
+                    continue;
+                };
+                writeln!(out, r#"  <rect x="{}" y="{}" width="{2}" height="{2}" fill="{3}" stroke="{4}" stroke-width="{5}"/>"#,
+                    margin + col * step, margin + row * step, style.sticker, fill, style.border_color, style.border).unwrap();
+            }
+        }
+        close(&mut out);
+        out
+    }
+}
+
+// How the cube is looked at. Angles are in degrees, the yaw turns the
+// front to the left and the pitch tips the top towards the viewer, both
+// between 0 and 90 keep U, F and R in sight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Isometric { yaw: f64, pitch: f64 },
+    // U from above with the top stickers of the sides around it
+    LastLayer
+}
+
+impl Projection {
+    pub const ISOMETRIC: Projection = Projection::Isometric { yaw: 45.0, pitch: 35.264 };
+}
+
+// From the centre of a sticker to the centre of another, with a head at
+// both ends for swaps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrow {
+    pub from: Facelet,
+    pub to: Facelet,
+    pub both_ways: bool
+}
+
+type Point = (f64, f64);
+
+// Where (u, v) of a U, F or R facelet is, the cube spans -1.5 to 1.5 with
+// x to R, y to U and z to F
+fn position(facelet: Facelet, u: f64, v: f64) -> Option<[f64; 3]> {
+    let (row, col) = (facelet.0 as f64, facelet.1 as f64);
+    match facelet {
+        (0..=2, 3..=5) => Some([col - 4.5 + u, 1.5, row - 1.5 + v]),
+        (3..=5, 3..=5) => Some([col - 4.5 + u, 4.5 - row - v, 1.5]),
+        (3..=5, 6..=8) => Some([1.5, 4.5 - row - v, 7.5 - col - u]),
+        _ => None
+    }
+}
+
+fn project([x, y, z]: [f64; 3], yaw: f64, pitch: f64) -> Point {
+    let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+    let (x, z) = (x * yaw.cos() - z * yaw.sin(), x * yaw.sin() + z * yaw.cos());
+    (x, z * pitch.sin() - y * pitch.cos())
+}
+
+// Corners of a sticker, shrunk by `inset` on each side
+fn sticker_points(facelet: Facelet, inset: f64, to_point: &dyn Fn([f64; 3]) -> Point) -> Option<Vec<Point>> {
+    [(inset, inset), (1.0 - inset, inset), (1.0 - inset, 1.0 - inset), (inset, 1.0 - inset)].iter()
+        .map(|&(u, v)| position(facelet, u, v).map(to_point))
+        .collect()
+}
+
+fn polygon(out: &mut String, points: &[Point], fill: &str, style: &SvgStyle) {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+    writeln!(out, r#"  <polygon points="{}" fill="{}" stroke="{}" stroke-width="{}"/>"#,
+        points.join(" "), fill, style.border_color, style.border).unwrap();
+}
+
+fn arrows(out: &mut String, lines: &[(Point, Point, bool)], style: &SvgStyle) {
+    if lines.is_empty() {
+        return;
+    }
+    writeln!(out, concat!(r#"  <defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" "#,
+        r#"markerWidth="4" markerHeight="4" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="{}"/></marker></defs>"#),
+        style.border_color).unwrap();
+    for &((x1, y1), (x2, y2), both_ways) in lines {
+        let start = if both_ways { r#" marker-start="url(#arrow)""# } else { "" };
+        writeln!(out, r#"  <line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{}"{} marker-end="url(#arrow)"/>"#,
+            x1, y1, x2, y2, style.border_color, style.sticker / 10 + 1, start).unwrap();
+    }
+}
+
+// The last layer from above, in stickers: U takes 1 to 4 each way, the
+// side stickers a third of a sticker around it. Cells of the sides run
+// the way the net has them.
+fn last_layer_cells() -> Vec<(Facelet, [f64; 4])> {
+    let strip = 1.0 / 3.0;
+    let mut cells = Vec::new();
+    for row in 0..3 {
+        for col in 0..3 {
+            cells.push(((row, col + 3), [1.0 + col as f64, 1.0 + row as f64, 1.0, 1.0]));
+        }
+    }
+    for i in 0..3 {
+        let at = 1.0 + i as f64;
+        cells.push(((3, 11 - i), [at, 1.0 - strip, 1.0, strip]));
+        cells.push(((3, 8 - i), [4.0, at, strip, 1.0]));
+        cells.push(((3, 3 + i), [at, 4.0, 1.0, strip]));
+        cells.push(((3, i), [1.0 - strip, at, strip, 1.0]));
+    }
+    cells
+}
+
+// Turns the points to fit the picture with a margin all round
+fn fit(points: &mut [Point], margin: f64) -> (usize, usize) {
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    for point in points.iter_mut() {
+        *point = (point.0 - min_x + margin, point.1 - min_y + margin);
+    }
+    let max_x = points.iter().map(|p| p.0).fold(0.0, f64::max);
+    let max_y = points.iter().map(|p| p.1).fold(0.0, f64::max);
+    ((max_x + margin).ceil() as usize, (max_y + margin).ceil() as usize)
+}
+
+impl Cube {
+    // U, F and R in 3D, or the last layer from above. Arrows off the
+    // faces in sight are left out.
+    pub fn to_view_svg(&self, style: &SvgStyle, projection: Projection, arrows_to_draw: &[Arrow]) -> String {
+        let size = style.sticker as f64;
+        let inset = style.gap as f64 / 2.0 / size;
+        // each shape is a fill and its points, the arrows come after
+        let mut shapes: Vec<(Option<Stiker>, Vec<Point>)> = Vec::new();
+        let mut both_ways = Vec::new();
+        match projection {
+            Projection::Isometric { yaw, pitch } => {
+                let to_point = |p: [f64; 3]| {
+                    let (x, y) = project(p, yaw, pitch);
+                    (x * size, y * size)
+                };
+                // the body behind the stickers
+                for face in [(0, 3), (3, 3), (3, 6)] {
+                    let corners = [(face, 0.0, 0.0), ((face.0, face.1 + 2), 1.0, 0.0),
+                        ((face.0 + 2, face.1 + 2), 1.0, 1.0), ((face.0 + 2, face.1), 0.0, 1.0)];
+                    let points = corners.iter().filter_map(|&(f, u, v)| position(f, u, v).map(to_point)).collect();
+                    shapes.push((None, points));
+                }
+                for (row, col) in (0..6).flat_map(|row| (3..9).map(move |col| (row, col))) {
+                    if let Some(points) = sticker_points((row, col), inset, &to_point) {
+                        shapes.push((Some(self.scan[row][col]), points));
+                    }
+                }
+                for arrow in arrows_to_draw {
+                    if let (Some(from), Some(to)) = (position(arrow.from, 0.5, 0.5), position(arrow.to, 0.5, 0.5)) {
+                        shapes.push((None, vec![to_point(from), to_point(to)]));
+                        both_ways.push(arrow.both_ways);
+                    }
+                }
+            }
+            Projection::LastLayer => {
+                let cells = last_layer_cells();
+                let gap = style.gap as f64 / 2.0;
+                for &((row, col), [x, y, w, h]) in &cells {
+                    let (x, y, w, h) = (x * size + gap, y * size + gap, w * size - 2.0 * gap, h * size - 2.0 * gap);
+                    shapes.push((Some(self.scan[row][col]), vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]));
+                }
+                let centre = |f: Facelet| cells.iter()
+                    .find(|(cell, _)| *cell == f && face_of(f) == 0 && f.0 < 3)
+                    .map(|(_, [x, y, _, _])| ((x + 0.5) * size, (y + 0.5) * size));
+                for arrow in arrows_to_draw {
+                    if let (Some(from), Some(to)) = (centre(arrow.from), centre(arrow.to)) {
+                        shapes.push((None, vec![from, to]));
+                        both_ways.push(arrow.both_ways);
+                    }
+                }
+            }
+        }
+        let mut points: Vec<Point> = shapes.iter().flat_map(|(_, p)| p.iter().copied()).collect();
+        let (width, height) = fit(&mut points, style.border as f64 + 1.0);
+        let mut points = points.into_iter();
+        let mut out = String::new();
+        open(&mut out, width, height);
+        let mut both_ways = both_ways.into_iter();
+        let mut lines = Vec::new();
+        for (fill, shape) in &shapes {
+            let shape: Vec<Point> = points.by_ref().take(shape.len()).collect();
+            match (fill, shape.len()) {
+                (_, 2) => lines.push((shape[0], shape[1], both_ways.next().unwrap_or(false))),
+                (None, _) => polygon(&mut out, &shape, &style.border_color, style),
+                (Some(stiker), _) => polygon(&mut out, &shape, style.fill(*stiker).unwrap_or(&style.grey), style)
+            }
+        }
+        arrows(&mut out, &lines, style);
+        close(&mut out);
+        out
+    }
+}
+
+// Which stickers of the last layer pieces a diagram shows, the rest are
+// grey like the stickers of pieces from outside the last layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mask {
+    All,
+    // the U coloured stickers only, for OLL
+    Orientation,
+    // for CMLL and COLL, where the edges do not count
+    Corners,
+    Edges
+}
+
+// An arrow from each U slot to where its piece belongs, one with two heads
+// for a swap
+fn permutation_arrows(perm: &[u8], facelets: &[Facelet]) -> Vec<Arrow> {
+    let mut arrows = Vec::new();
+    for (slot, &piece) in perm.iter().enumerate() {
+        let piece = piece as usize;
+        if piece == slot || piece >= facelets.len() {
+            continue;
+        }
+        let both_ways = perm[piece] as usize == slot;
+        if both_ways && piece < slot {
+            continue;
+        }
+        arrows.push(Arrow { from: facelets[slot], to: facelets[piece], both_ways });
+    }
+    arrows
+}
+
+// The last layer from above, arrows showing where the pieces go to be
+// solved. Without a cubie reading of the stickers there are no arrows.
+pub fn last_layer_diagram(cube: &Cube, style: &SvgStyle, mask: Mask) -> String {
+    let top = cube.centre_colors()[0];
+    let mut shown = cube.clone();
+    let (corners, edges) = (mask != Mask::Edges, mask != Mask::Corners);
+    let pieces = CORNER_FACELETS[..4].iter().map(|f| (&f[..], corners))
+        .chain(EDGE_FACELETS[..4].iter().map(|f| (&f[..], edges)));
+    for (facelets, counts) in pieces {
+        let in_layer = facelets.iter().any(|&f| cube.color(f) == top);
+        for &(row, col) in facelets {
+            let oriented = mask != Mask::Orientation || cube.color((row, col)) == top;
+            if !(in_layer && counts && oriented) {
+                shown.scan[row][col] = Stiker::V;
+            }
+        }
+    }
+    let mut arrows = Vec::new();
+    if let Some(cubie) = CubieCube::from_cube(cube).filter(|_| mask != Mask::Orientation) {
+        let corner_tops: Vec<Facelet> = CORNER_FACELETS[..4].iter().map(|f| f[0]).collect();
+        let edge_tops: Vec<Facelet> = EDGE_FACELETS[..4].iter().map(|f| f[0]).collect();
+        if corners {
+            arrows.extend(permutation_arrows(&cubie.cp[..4], &corner_tops));
+        }
+        if edges {
+            arrows.extend(permutation_arrows(&cubie.ep[..4], &edge_tops));
+        }
+    }
+    shown.to_view_svg(style, Projection::LastLayer, &arrows)
+}
+
+// The case the algorithm solves, undone from a solved cube
+pub fn last_layer_diagram_for(alg: &[Move], style: &SvgStyle, mask: Mask) -> String {
+    let mut cube = Cube::default();
+    cube.apply_alg(&invert(alg));
+    last_layer_diagram(&cube, style, mask)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    #[test]
+    fn check_to_svg() {
+        let svg = Cube::default().to_svg();
+        assert!(svg.starts_with(concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="264" height="198" viewBox="0 0 264 198">"#, "\n",
+            r##"  <rect x="67" y="1" width="20" height="20" fill="#ffd500" stroke="#000000" stroke-width="1"/>"##
+        )));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 54);
+        assert_eq!(svg, Cube::default().to_svg());
+        let style = SvgStyle { sticker: 10, gap: 0, border: 0, ..SvgStyle::default() };
+        let mut cube = Cube::from("R");
+        cube.scan[4][4] = Stiker::V;
+        let svg = cube.to_svg_with(&style);
+        assert!(svg.contains(r#"width="120" height="90""#));
+        assert_eq!(svg.matches("<rect").count(), 53);
+        assert!(svg.contains(r##"<rect x="50" y="0" width="10" height="10" fill="#0045ad""##));
+        assert_eq!(Cube { scan: [[Stiker::V; 12]; 9] }.to_svg_with(&style).matches("<rect").count(), 0);
+    }
+
+    #[test]
+    fn check_isometric() {
+        let style = SvgStyle::default();
+        let svg = Cube::default().to_view_svg(&style, Projection::ISOMETRIC, &[]);
+        assert_eq!(svg.matches("<polygon").count(), 30);
+        assert_eq!(svg.matches(r##"fill="#ffd500""##).count(), 9);
+        assert_eq!(svg.matches(r##"fill="#b90000""##).count(), 9);
+        assert!(!svg.contains("<line") && !svg.contains("<defs>"));
+        assert_eq!(svg, Cube::default().to_view_svg(&style, Projection::ISOMETRIC, &[]));
+        let tilted = Projection::Isometric { yaw: 30.0, pitch: 20.0 };
+        assert_ne!(svg, Cube::default().to_view_svg(&style, tilted, &[]));
+        // the white D face is out of sight until the cube is turned over
+        assert!(!svg.contains("#ffffff"));
+        let mut over = Cube::default();
+        over.apply_alg(&parse_alg("x2").unwrap());
+        assert!(over.to_view_svg(&style, Projection::ISOMETRIC, &[]).contains("#ffffff"));
+        let arrows = [Arrow { from: (2, 5), to: (0, 3), both_ways: false }, Arrow { from: (7, 4), to: (1, 4), both_ways: false }];
+        let svg = Cube::default().to_view_svg(&style, Projection::ISOMETRIC, &arrows);
+        assert_eq!((svg.matches("<line").count(), svg.matches("<marker").count()), (1, 1));
+    }
+
+    #[test]
+    fn check_last_layer_view() {
+        let style = SvgStyle { sticker: 30, gap: 2, ..SvgStyle::default() };
+        let svg = Cube::from("R U R' U R U2 R'").to_view_svg(&style, Projection::LastLayer, &[]);
+        assert_eq!(svg.matches("<polygon").count(), 21);
+        assert!(svg.contains(r#"width="112" height="112""#));
+        let solved = Cube::default().to_view_svg(&style, Projection::LastLayer, &[]);
+        for (fill, count) in [("#ffd500", 9), ("#009b48", 3), ("#b90000", 3), ("#0045ad", 3), ("#ff5900", 3)] {
+            assert_eq!(solved.matches(&format!(r#"fill="{}""#, fill)).count(), count, "{}", fill);
+        }
+        // the first side sticker is B next to L, over the left of U
+        assert!(solved.contains(r##"<polygon points="12.0,2.0 40.0,2.0 40.0,10.0 12.0,10.0" fill="#009b48""##));
+        let arrow = [Arrow { from: (0, 3), to: (2, 5), both_ways: false }];
+        let svg = Cube::default().to_view_svg(&style, Projection::LastLayer, &arrow);
+        assert!(svg.contains(r#"<line x1="26.0" y1="26.0" x2="86.0" y2="86.0""#));
+    }
+
+    #[test]
+    fn check_last_layer_diagram() {
+        let style = SvgStyle::default();
+        let count = |svg: &str, text: &str| svg.matches(text).count();
+        // Ua cycles three edges, the corners stay
+        let ua = last_layer_diagram_for(&parse_alg("M2 U M U2 M' U M2").unwrap(), &style, Mask::All);
+        assert_eq!((count(&ua, "<line"), count(&ua, "marker-start")), (3, 0));
+        assert_eq!(count(&ua, "#808080"), 0);
+        // T swaps two corners and two edges
+        let t = parse_alg("R U R' U' R' F R2 U' R' U' R U R' F'").unwrap();
+        let svg = last_layer_diagram_for(&t, &style, Mask::All);
+        assert_eq!((count(&svg, "<line"), count(&svg, "marker-start")), (2, 2));
+        let corners = last_layer_diagram_for(&t, &style, Mask::Corners);
+        assert_eq!((count(&corners, "<line"), count(&corners, "#808080")), (1, 8));
+        // OLL shows the U colour, no arrows
+        let sune = last_layer_diagram_for(&parse_alg("R U R' U R U2 R'").unwrap(), &style, Mask::Orientation);
+        assert_eq!((count(&sune, "#ffd500"), count(&sune, "#808080"), count(&sune, "<line")), (9, 12, 0));
+        // after R the DFR corner and the FR edge are up, greyed
+        let svg = last_layer_diagram(&Cube::from("R"), &style, Mask::All);
+        assert_eq!(count(&svg, "#808080"), 5);
+        assert_eq!(last_layer_diagram(&Cube::default(), &style, Mask::All).matches("<line").count(), 0);
+    }
+}
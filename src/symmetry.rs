@@ -0,0 +1,172 @@
+use std::sync::OnceLock;
+use crate::{Cube, Stiker};
+use crate::facelet::*;
+
+// The 48 symmetries are the 24 ways to hold the cube, in the order of
+// rotation_perms, then the same after a mirror that swaps L and R.
+
+pub const SYMMETRIES: usize = 48;
+
+fn mirror(facelet: Facelet) -> Facelet {
+    match facelet {
+        (row @ 3..=5, col @ 9..) => (row, 20 - col),
+        (row @ 3..=5, col) | (row, col @ 3..=5) => (row, 8 - col),
+        // off the net
+        other => other
+    }
+}
+
+fn symmetry_perms() -> &'static [Vec<u8>] {
+    static PERMS: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+    PERMS.get_or_init(|| {
+        let rotations = rotation_perms();
+        let mirrored = rotations.iter().map(|perm| {
+            (0..STICKERS as u8).map(|s| perm[sticker(mirror(facelet(s))) as usize]).collect()
+        });
+        rotations.iter().cloned().chain(mirrored).collect()
+    })
+}
+
+impl Cube {
+    // The whole cube turned, colours and all
+    fn rotated(&self, perm: &[u8]) -> Cube {
+        let mut cube = Cube { scan: [[Stiker::V; 12]; 9] };
+        for s in 0..STICKERS as u8 {
+            let to = facelet(perm[s as usize]);
+            cube.scan[to.0][to.1] = self.color(facelet(s));
+        }
+        cube
+    }
+    pub fn eq_up_to_rotation(&self, other: &Cube) -> bool {
+        rotation_perms().iter().any(|perm| self.rotated(perm) == *other)
+    }
+    // Equal, once the colours of one are renamed one to one
+    pub fn eq_up_to_recolor(&self, other: &Cube) -> bool {
+        let mut names: Vec<(Stiker, Stiker)> = Vec::new();
+        for s in 0..STICKERS as u8 {
+            let pair = (self.color(facelet(s)), other.color(facelet(s)));
+            if names.iter().any(|&(a, b)| (a == pair.0) != (b == pair.1)) {
+                return false;
+            }
+            if !names.contains(&pair) {
+                names.push(pair);
+            }
+        }
+        true
+    }
+    // Turns the whole cube, so that the centres are where the default
+    // scheme has them, like after M. False if no way of holding it does.
+    pub fn normalize_orientation(&mut self) -> bool {
+        let scheme = Cube::default().centre_colors();
+        let found = rotation_perms().iter()
+            .map(|perm| self.rotated(perm))
+            .find(|cube| cube.centre_colors() == scheme);
+        if let Some(cube) = found {
+            *self = cube;
+        }
+        self.centre_colors() == scheme
+    }
+    // The same state seen through the symmetry: every sticker goes where
+    // the symmetry takes it, and takes the colour of the face the symmetry
+    // takes its face to, so the solved cube stays solved
+    pub fn symmetry(&self, index: usize) -> Cube {
+        let perm = &symmetry_perms()[index];
+        let scheme = self.centre_colors();
+        let faces: Vec<usize> = CENTER_FACELETS.iter()
+            .map(|&c| face_of(facelet(perm[sticker(c) as usize])))
+            .collect();
+        let mut cube = Cube { scan: [[Stiker::V; 12]; 9] };
+        for s in 0..STICKERS as u8 {
+            let color = self.color(facelet(s));
+            let to = facelet(perm[s as usize]);
+            cube.scan[to.0][to.1] = match scheme.iter().position(|&c| c == color) {
+                Some(face) => scheme[faces[face]],
+                None => color
+            };
+        }
+        cube
+    }
+    // The least of the states the symmetries give, by the letters of the
+    // scan, so cubes that are the same up to symmetry get the same one
+    pub fn canonical(&self) -> Cube {
+        (0..SYMMETRIES).map(|i| self.symmetry(i))
+            .min_by_key(|cube| cube.scan.map(|row| row.map(char::from)))
+            .unwrap()
+    }
+    // How many symmetries leave the state as it is
+    pub fn stabiliser(&self) -> usize {
+        (0..SYMMETRIES).filter(|&i| self.symmetry(i) == *self).count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubie::CubieCube;
+    use crate::notation::parse_alg;
+
+    const SUPERFLIP: &str = "U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2";
+
+    #[test]
+    fn check_symmetry() {
+        let solved = Cube::default();
+        assert!((0..SYMMETRIES).all(|i| solved.symmetry(i) == solved));
+        let r = Cube::from("R");
+        let images: Vec<Cube> = (0..SYMMETRIES).map(|i| r.symmetry(i)).collect();
+        for turn in ["U", "U'", "L", "L'", "F", "B'", "D"] {
+            assert!(images.contains(&Cube::from(turn)), "{}", turn);
+        }
+        assert!(!images.contains(&Cube::from("R2")));
+        let scramble = Cube::from("R U2 F' L D B2");
+        assert!((0..SYMMETRIES).all(|i| CubieCube::from_cube(&scramble.symmetry(i)).is_some_and(|c| c.is_solvable())));
+    }
+
+    #[test]
+    fn check_canonical() {
+        assert_eq!(Cube::from("R").canonical(), Cube::from("L'").canonical());
+        assert_eq!(Cube::from("R U").canonical(), Cube::from("U F").canonical());
+        assert_ne!(Cube::from("R").canonical(), Cube::from("R2").canonical());
+        let cube = Cube::from("R U2 F' L D B2");
+        assert_eq!(cube.symmetry(30).canonical(), cube.canonical());
+    }
+
+    #[test]
+    fn check_equality() {
+        let mut turned = Cube::from("R U F'");
+        turned.apply_alg(&parse_alg("x y'").unwrap());
+        assert!(turned != Cube::from("R U F'"));
+        assert!(turned.eq_up_to_rotation(&Cube::from("R U F'")));
+        assert!(!turned.eq_up_to_rotation(&Cube::from("R U F")));
+        let mut recolored = Cube::from("R U F'");
+        for row in recolored.scan.iter_mut() {
+            for color in row.iter_mut() {
+                *color = match *color {
+                    Stiker::B => Stiker::G,
+                    Stiker::G => Stiker::B,
+                    other => other
+                };
+            }
+        }
+        assert!(recolored.eq_up_to_recolor(&Cube::from("R U F'")));
+        assert!(!recolored.eq_up_to_recolor(&Cube::from("R U F")));
+        assert!(!Cube::from("R").eq_up_to_recolor(&Cube::default()));
+        let mut drifted = Cube::from("M");
+        assert!(drifted.normalize_orientation());
+        assert_eq!(drifted, Cube::from("R L'"));
+        let mut masked = Cube::default();
+        masked.scan[1][4] = Stiker::V;
+        assert!(!masked.normalize_orientation());
+    }
+
+    #[test]
+    fn check_stabiliser() {
+        let superflip = Cube::from(SUPERFLIP);
+        let cubie = CubieCube::from_cube(&superflip).unwrap();
+        assert!(cubie.eo.iter().all(|&o| o == 1) && cubie.ep == CubieCube::default().ep);
+        assert_eq!(superflip.stabiliser(), 48);
+        assert_eq!(Cube::default().stabiliser(), 48);
+        assert_eq!(Cube::from("R2 L2 U2 D2 F2 B2").stabiliser(), 48);
+        assert_eq!(Cube::from("U").stabiliser(), 4);
+        assert_eq!(Cube::from("R U2 F' L D B2").stabiliser(), 1);
+    }
+}
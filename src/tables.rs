@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Generated tables are kept as files in a cache directory, so they are
+// built once and not on every start. A file is
+//
+// magic "CUBT", format version (u32), payload length (u64),
+// FNV-1a checksum of the payload (u64), payload
+//
+// all little endian. Files of another version, or that do not check out,
+// are built again and overwritten. Bump the version whenever the way any
+// table is built changes.
+
+pub const FORMAT_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"CUBT";
+const HEADER: usize = 24;
+
+pub(crate) trait Table: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(input: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], size: usize) -> Option<&'a [u8]> {
+    if input.len() < size {
+        return None;
+    }
+    let (head, rest) = input.split_at(size);
+    *input = rest;
+    Some(head)
+}
+
+macro_rules! number_table {
+    ($($t:ty),*) => {$(
+        impl Table for $t {
+            fn write(&self, out: &mut Vec<u8>) {
+                out.extend(self.to_le_bytes());
+            }
+            fn read(input: &mut &[u8]) -> Option<Self> {
+                Some(<$t>::from_le_bytes(take(input, size_of::<$t>())?.try_into().ok()?))
+            }
+        }
+    )*}
+}
+
+number_table!(u8, u16, u32, u64, u128);
+
+impl Table for usize {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u64).write(out);
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        usize::try_from(u64::read(input)?).ok()
+    }
+}
+
+impl Table for bool {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u8).write(out);
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        match u8::read(input)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None
+        }
+    }
+}
+
+impl<T: Table> Table for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        for item in self {
+            item.write(out);
+        }
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::read(input)?;
+        // every item takes a byte at least
+        if len > input.len() {
+            return None;
+        }
+        (0..len).map(|_| T::read(input)).collect()
+    }
+}
+
+impl<T: Table, const N: usize> Table for [T; N] {
+    fn write(&self, out: &mut Vec<u8>) {
+        for item in self {
+            item.write(out);
+        }
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        let items: Vec<T> = (0..N).map(|_| T::read(input)).collect::<Option<_>>()?;
+        items.try_into().ok()
+    }
+}
+
+impl<K: Table + Hash + Eq, V: Table> Table for HashMap<K, V> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        for (key, value) in self {
+            key.write(out);
+            value.write(out);
+        }
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::read(input)?;
+        if len > input.len() {
+            return None;
+        }
+        (0..len).map(|_| Some((K::read(input)?, V::read(input)?))).collect()
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn encode<T: Table>(table: &T) -> Vec<u8> {
+    let mut payload = Vec::new();
+    table.write(&mut payload);
+    let mut bytes = Vec::with_capacity(HEADER + payload.len());
+    bytes.extend(MAGIC);
+    FORMAT_VERSION.write(&mut bytes);
+    (payload.len() as u64).write(&mut bytes);
+    checksum(&payload).write(&mut bytes);
+    bytes.extend(payload);
+    bytes
+}
+
+fn decode<T: Table>(bytes: &[u8]) -> Option<T> {
+    let mut input = bytes;
+    if take(&mut input, 4)? != MAGIC || u32::read(&mut input)? != FORMAT_VERSION {
+        return None;
+    }
+    let len = u64::read(&mut input)?;
+    let sum = u64::read(&mut input)?;
+    if input.len() as u64 != len || checksum(input) != sum {
+        return None;
+    }
+    let table = T::read(&mut input)?;
+    input.is_empty().then_some(table)
+}
+
+#[derive(Debug, Clone)]
+pub struct TableCache {
+    dir: PathBuf
+}
+
+impl TableCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        TableCache { dir: dir.into() }
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.table", name))
+    }
+    // None if the file is missing, of another version or corrupt
+    pub(crate) fn load<T: Table>(&self, name: &str) -> Option<T> {
+        decode(&fs::read(self.path(name)).ok()?)
+    }
+    // Written next to the target first, so a crash never leaves half a file
+    pub(crate) fn store<T: Table>(&self, name: &str, table: &T) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(name);
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp, encode(table))?;
+        fs::rename(&temp, &path)
+    }
+    // A cache that can not be written to only costs the time to build
+    pub(crate) fn get_or_build<T: Table>(&self, name: &str, build: impl FnOnce() -> T) -> T {
+        if let Some(table) = self.load(name) {
+            return table;
+        }
+        let table = build();
+        let _ = self.store(name, &table);
+        table
+    }
+}
+
+static CACHE: Mutex<Option<TableCache>> = Mutex::new(None);
+
+// Tables are only cached once a directory is set, and only those built
+// after that
+pub fn set_cache_dir(dir: impl Into<PathBuf>) {
+    *CACHE.lock().unwrap() = Some(TableCache::new(dir));
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    CACHE.lock().unwrap().as_ref().map(|cache| cache.dir.clone())
+}
+
+pub(crate) fn cached<T: Table>(name: &str, build: impl FnOnce() -> T) -> T {
+    let cache = CACHE.lock().unwrap().clone();
+    match cache {
+        Some(cache) => cache.get_or_build(name, build),
+        None => build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_cache(name: &str) -> TableCache {
+        let dir = std::env::temp_dir().join(format!("cube_tables_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        TableCache::new(dir)
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let table: HashMap<[u8; 3], u8> = HashMap::from([([0, 1, 2], 0), ([2, 1, 0], 5)]);
+        let tables = [vec![1usize, 2], vec![]];
+        let cache = temp_cache("round_trip");
+        cache.store("map", &table).unwrap();
+        cache.store("vecs", &tables).unwrap();
+        assert_eq!(cache.load::<HashMap<[u8; 3], u8>>("map"), Some(table));
+        assert_eq!(cache.load::<[Vec<usize>; 2]>("vecs"), Some(tables));
+        assert_eq!(cache.load::<u8>("missing"), None);
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn check_rebuild() {
+        let cache = temp_cache("rebuild");
+        let builds = std::cell::Cell::new(0);
+        let build = || {
+            builds.set(builds.get() + 1);
+            vec![7u16; 100]
+        };
+        assert_eq!(cache.get_or_build("table", build), vec![7; 100]);
+        assert_eq!(cache.get_or_build("table", build), vec![7; 100]);
+        assert_eq!(builds.get(), 1);
+        let path = cache.path("table");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER + 20] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(cache.load::<Vec<u16>>("table"), None);
+        bytes[HEADER + 20] ^= 1;
+        bytes[4] += 1;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(cache.load::<Vec<u16>>("table"), None);
+        fs::write(&path, &bytes[..30]).unwrap();
+        assert_eq!(cache.get_or_build("table", build), vec![7; 100]);
+        assert_eq!(builds.get(), 2);
+        assert_eq!(cache.load::<Vec<u16>>("table"), Some(vec![7; 100]));
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
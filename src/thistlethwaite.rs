@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::cubie::{CubieCube, distances, search};
+use crate::notation::{Layer::*, Move, alg_to_string};
+use crate::tables::{Table, cached};
+
+// The usual chain, with F/B as the first axis to lose its quarter turns
+// (as edge orientation is read on F/B everywhere else in the crate):
+//
+// G0 = <U, D, L, R, F, B>
+// G1 = <U, D, L, R, F2, B2>     edges oriented
+// G2 = <U, D, L2, R2, F2, B2>   corners oriented, E slice edges in E
+// G3 = <U2, D2, L2, R2, F2, B2> corners in a half turn permutation,
+//                               M slice edges in M
+// G4 = solved
+
+const E_SLICE: [u8; 4] = [8, 9, 10, 11];   // FR FL BL BR
+const M_SLICE: [u8; 4] = [1, 3, 5, 7];     // UF UB DF DB
+
+fn phase_moves(phase: usize) -> Vec<Move> {
+    let half = |layers: &[_]| layers.iter().map(|&l| Move::new(l, 2)).collect::<Vec<_>>();
+    match phase {
+        0 => Move::all(&[U, D, L, R, F, B]),
+        1 => [Move::all(&[U, D, L, R]), half(&[F, B])].concat(),
+        2 => [Move::all(&[U, D]), half(&[L, R, F, B])].concat(),
+        _ => half(&[U, D, L, R, F, B])
+    }
+}
+
+fn slots_of(cubie: &CubieCube, pieces: &[u8]) -> u16 {
+    (0..12).filter(|&slot| pieces.contains(&cubie.ep[slot]))
+        .fold(0, |mask, slot| mask | 1 << slot)
+}
+
+// Corner permutations of G3, there are 96 of them
+fn half_turn_corners() -> &'static HashMap<[u8; 8], u8> {
+    static CORNERS: OnceLock<HashMap<[u8; 8], u8>> = OnceLock::new();
+    CORNERS.get_or_init(|| distances(&[CubieCube::default()], &phase_moves(3), |c| c.cp))
+}
+
+fn g1(cubie: &CubieCube) -> bool {
+    cubie.eo == [0; 12]
+}
+
+fn g2(cubie: &CubieCube) -> bool {
+    g1(cubie) && cubie.co == [0; 8] && slots_of(cubie, &E_SLICE) == 0xf00
+}
+
+fn g3(cubie: &CubieCube) -> bool {
+    g2(cubie) && half_turn_corners().contains_key(&cubie.cp)
+        && slots_of(cubie, &M_SLICE) == 0b10101010
+}
+
+fn in_group(cube: &Cube, group: fn(&CubieCube) -> bool) -> bool {
+    CubieCube::from_cube(cube).is_some_and(|c| group(&c))
+}
+
+pub fn is_in_g1(cube: &Cube) -> bool {
+    in_group(cube, g1)
+}
+
+pub fn is_in_g2(cube: &Cube) -> bool {
+    in_group(cube, g2)
+}
+
+pub fn is_in_g3(cube: &Cube) -> bool {
+    in_group(cube, g3)
+}
+
+fn group_name(cubie: &CubieCube) -> &'static str {
+    if *cubie == CubieCube::default() {
+        "G4"
+    } else if g3(cubie) {
+        "G3"
+    } else if g2(cubie) {
+        "G2"
+    } else if g1(cubie) {
+        "G1"
+    } else {
+        "G0"
+    }
+}
+
+struct Tables {
+    edges_oriented: HashMap<[u8; 12], u8>,
+    corners_oriented: HashMap<[u8; 8], u8>,
+    e_slice: HashMap<u16, u8>,
+    corners_halved: HashMap<[u8; 8], u8>,
+    m_slice: HashMap<u16, u8>,
+    corners_solved: HashMap<[u8; 8], u8>,
+    edges_solved: HashMap<[u8; 12], u8>
+}
+
+impl Table for Tables {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.edges_oriented.write(out);
+        self.corners_oriented.write(out);
+        self.e_slice.write(out);
+        self.corners_halved.write(out);
+        self.m_slice.write(out);
+        self.corners_solved.write(out);
+        self.edges_solved.write(out);
+    }
+    fn read(input: &mut &[u8]) -> Option<Self> {
+        Some(Tables {
+            edges_oriented: Table::read(input)?,
+            corners_oriented: Table::read(input)?,
+            e_slice: Table::read(input)?,
+            corners_halved: Table::read(input)?,
+            m_slice: Table::read(input)?,
+            corners_solved: Table::read(input)?,
+            edges_solved: Table::read(input)?
+        })
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| cached("thistlethwaite", || {
+        let solved = [CubieCube::default()];
+        let halved: Vec<CubieCube> = half_turn_corners().keys()
+            .map(|&cp| CubieCube { cp, ..CubieCube::default() })
+            .collect();
+        Tables {
+            edges_oriented: distances(&solved, &phase_moves(0), |c| c.eo),
+            corners_oriented: distances(&solved, &phase_moves(1), |c| c.co),
+            e_slice: distances(&solved, &phase_moves(1), |c| slots_of(c, &E_SLICE)),
+            corners_halved: distances(&halved, &phase_moves(2), |c| c.cp),
+            m_slice: distances(&solved, &phase_moves(2), |c| slots_of(c, &M_SLICE)),
+            corners_solved: distances(&solved, &phase_moves(3), |c| c.cp),
+            edges_solved: distances(&solved, &phase_moves(3), |c| c.ep)
+        }
+    }))
+}
+
+fn bound(phase: usize, c: &CubieCube) -> u8 {
+    let t = tables();
+    let get = |distance: Option<&u8>| distance.copied().unwrap_or(u8::MAX);
+    match phase {
+        0 => get(t.edges_oriented.get(&c.eo)),
+        1 => get(t.corners_oriented.get(&c.co)).max(get(t.e_slice.get(&slots_of(c, &E_SLICE)))),
+        2 => get(t.corners_halved.get(&c.cp)).max(get(t.m_slice.get(&slots_of(c, &M_SLICE)))),
+        _ => get(t.corners_solved.get(&c.cp)).max(get(t.edges_solved.get(&c.ep)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub moves: Vec<Move>,
+    pub group: &'static str
+}
+
+#[derive(Debug, Clone)]
+pub struct ThistlethwaiteSolution {
+    pub start: &'static str,
+    pub phases: Vec<Phase>
+}
+
+impl ThistlethwaiteSolution {
+    pub fn moves(&self) -> Vec<Move> {
+        self.phases.iter().flat_map(|p| p.moves.clone()).collect()
+    }
+}
+
+impl fmt::Display for ThistlethwaiteSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut group = self.start;
+        for phase in &self.phases {
+            writeln!(f, "{} -> {}: {} ({} moves)", group, phase.group,
+                alg_to_string(&phase.moves), phase.moves.len())?;
+            group = phase.group;
+        }
+        write!(f, "Total: {} moves", self.moves().len())
+    }
+}
+
+pub fn solve(cube: &Cube) -> Option<ThistlethwaiteSolution> {
+    let mut cubie = CubieCube::from_cube(cube)?;
+    if !cubie.is_solvable() {
+        return None;
+    }
+    let start = group_name(&cubie);
+    let goals: [fn(&CubieCube) -> bool; 4] = [g1, g2, g3, |c| *c == CubieCube::default()];
+    let limits = [7, 10, 13, 15];
+    let mut phases = Vec::new();
+    for phase in 0..4 {
+        let moves = search(&cubie, &phase_moves(phase), &|c| bound(phase, c), &goals[phase], limits[phase])?;
+        cubie.apply_alg(&moves);
+        phases.push(Phase { moves, group: group_name(&cubie) });
+    }
+    Some(ThistlethwaiteSolution { start, phases })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+
+    #[test]
+    fn check_groups() {
+        let cube = |alg| {
+            let mut cube = Cube::default();
+            cube.apply_alg(&parse_alg(alg).unwrap());
+            cube
+        };
+        assert!(is_in_g3(&cube("R2 U2 F2 D2 L2 B2")));
+        assert!(!is_in_g3(&cube("R2 U")) && is_in_g2(&cube("R2 U")));
+        assert!(!is_in_g2(&cube("R")) && is_in_g1(&cube("R")));
+        assert!(!is_in_g1(&cube("F")));
+        assert!(!is_in_g3(&cube("U R2 U'")));
+        assert_eq!(half_turn_corners().len(), 96);
+    }
+
+    #[test]
+    fn check_solve() {
+        let mut cube = Cube::from(SCRAMBLE);
+        let solution = solve(&cube).unwrap();
+        assert_eq!(solution.start, "G0");
+        let groups: Vec<_> = solution.phases.iter().map(|p| p.group).collect();
+        assert_eq!(groups, ["G1", "G2", "G3", "G4"]);
+        for (phase, step) in solution.phases.iter().enumerate() {
+            assert!(step.moves.iter().all(|m| phase_moves(phase).contains(m)));
+        }
+        cube.apply_alg(&solution.moves());
+        assert_eq!(cube, Cube::default());
+    }
+}
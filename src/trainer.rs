@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use crate::Cube;
+use crate::algdb::{AlgCase, library};
+use crate::notation::{Layer::U, Move, invert, simplify};
+use crate::random::{Random, SplitMix};
+use crate::thistlethwaite;
+
+// Drills the cases of an algorithm set, or a part of it, picking the ones
+// missed most often more often.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Record {
+    pub hits: u32,
+    pub misses: u32
+}
+
+impl Record {
+    // the share of misses, guessed a half before the first try
+    fn weight(&self) -> f64 {
+        (self.misses as f64 + 1.0) / ((self.hits + self.misses) as f64 + 2.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Drill {
+    pub case: &'static AlgCase,
+    // a scramble for the case, that does not give the algorithm away
+    pub setup: Vec<Move>,
+    pub cube: Cube,
+    // the algorithms of the case with the AUFs they take from the setup,
+    // the first is the expected answer
+    pub answers: Vec<Vec<Move>>
+}
+
+pub struct Trainer<R: Random = SplitMix> {
+    cases: Vec<&'static AlgCase>,
+    records: HashMap<&'static str, Record>,
+    random: R
+}
+
+impl Trainer {
+    // None unless the library has the set
+    pub fn new(set: &str) -> Option<Self> {
+        let cases: Vec<_> = library().iter().filter(|c| c.set == set).collect();
+        if cases.is_empty() {
+            return None;
+        }
+        Some(Trainer { cases, records: HashMap::new(), random: SplitMix::from_time() })
+    }
+}
+
+impl<R: Random> Trainer<R> {
+    pub fn with_random<S: Random>(self, random: S) -> Trainer<S> {
+        Trainer { cases: self.cases, records: self.records, random }
+    }
+    pub fn only(mut self, cases: &[&str]) -> Self {
+        self.cases.retain(|c| cases.contains(&c.case));
+        self
+    }
+    pub fn without(mut self, cases: &[&str]) -> Self {
+        self.cases.retain(|c| !cases.contains(&c.case));
+        self
+    }
+    pub fn cases(&self) -> Vec<&'static str> {
+        self.cases.iter().map(|c| c.case).collect()
+    }
+    pub fn record(&mut self, case: &str, hit: bool) {
+        let Some(found) = self.cases.iter().find(|c| c.case == case) else {
+            return;
+        };
+        let record = self.records.entry(found.case).or_default();
+        if hit {
+            record.hits += 1;
+        } else {
+            record.misses += 1;
+        }
+    }
+    pub fn record_of(&self, case: &str) -> Record {
+        self.records.get(case).copied().unwrap_or_default()
+    }
+    fn pick(&mut self) -> Option<&'static AlgCase> {
+        let weights: Vec<f64> = self.cases.iter().map(|c| self.record_of(c.case).weight()).collect();
+        let mut left = self.random.unit() * weights.iter().sum::<f64>();
+        for (case, weight) in self.cases.iter().zip(&weights) {
+            if left < *weight {
+                return Some(case);
+            }
+            left -= weight;
+        }
+        self.cases.last().copied()
+    }
+    // A case of the subset with a random AUF, None if the subset is empty.
+    // An AUF, that gets no setup of its own, gives way to the next one.
+    pub fn drill(&mut self) -> Option<Drill> {
+        let case = self.pick()?;
+        let first = self.random.below(4) as u8;
+        (0..4).find_map(|turn| drill_with(case, Move::new(U, (first + turn) % 4)))
+    }
+}
+
+// The setup, if it gives the cube and is none of the answers backwards
+fn disguise(setup: Vec<Move>, cube: &Cube, answers: &[Vec<Move>]) -> Option<Vec<Move>> {
+    let mut check = Cube::default();
+    check.apply_alg(&setup);
+    let plain = answers.iter().any(|answer| simplify(&invert(answer)) == simplify(&setup));
+    (check == *cube && !plain).then_some(setup)
+}
+
+fn drill_with(case: &'static AlgCase, auf: Move) -> Option<Drill> {
+    let mut answers: Vec<Vec<Move>> = case.algs.iter()
+        .map(|alg| simplify(&[&[auf][..], alg].concat()))
+        .collect();
+    let mut cube = Cube::default();
+    cube.apply_alg(&invert(&answers[0]));
+    // the solver's moves only turn faces, and start nowhere near the
+    // algorithm
+    let solution = thistlethwaite::solve(&cube)?;
+    let setup = disguise(invert(&solution.moves()), &cube, &answers)?;
+    // the turn of U that finishes the cube, for the sets that solve it
+    for answer in &mut answers {
+        let mut solved = cube.clone();
+        solved.apply_alg(answer);
+        if let Some(turn) = (1..4).map(|t| Move::new(U, t)).find(|&t| {
+            let mut turned = solved.clone();
+            turned.apply(t);
+            turned == Cube::default()
+        }) {
+            answer.push(turn);
+        }
+    }
+    Some(Drill { case, setup, cube, answers })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algdb::recognise;
+    use crate::notation::parse_alg;
+
+    #[test]
+    fn check_drill() {
+        let mut trainer = Trainer::new("PLL").unwrap().without(&["Na", "Nb"]).with_random(SplitMix::new(1));
+        assert_eq!(trainer.cases().len(), 19);
+        for _ in 0..8 {
+            let drill = trainer.drill().unwrap();
+            assert!(!["Na", "Nb"].contains(&drill.case.case));
+            let mut cube = Cube::default();
+            cube.apply_alg(&drill.setup);
+            assert_eq!(cube, drill.cube);
+            assert!(drill.setup.iter().all(|m| !m.layer.is_slice() && !m.layer.is_rotation()));
+            assert_eq!(recognise("PLL", &cube).unwrap().0, drill.case.case);
+            for answer in &drill.answers {
+                let mut solved = cube.clone();
+                solved.apply_alg(answer);
+                assert_eq!(solved, Cube::default());
+            }
+        }
+        assert!(Trainer::new("PLL").unwrap().only(&[]).drill().is_none());
+        assert!(Trainer::new("nothing").is_none());
+    }
+
+    #[test]
+    fn check_disguise() {
+        let answers = [parse_alg("R U R' U R U2 R'").unwrap()];
+        let mut cube = Cube::default();
+        cube.apply_alg(&invert(&answers[0]));
+        assert_eq!(disguise(invert(&answers[0]), &cube, &answers), None);
+        assert_eq!(disguise(parse_alg("R").unwrap(), &cube, &answers), None);
+        let setup = invert(&thistlethwaite::solve(&cube).unwrap().moves());
+        assert_eq!(disguise(setup.clone(), &cube, &answers), Some(setup));
+        for set in ["OLL", "PLL"] {
+            let mut trainer = Trainer::new(set).unwrap().with_random(SplitMix::new(4));
+            for _ in 0..20 {
+                let drill = trainer.drill().unwrap();
+                for answer in &drill.answers {
+                    assert_ne!(drill.setup, simplify(&invert(answer)), "{}", drill.case.case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_weights() {
+        let mut trainer = Trainer::new("OLL").unwrap().only(&["1", "2", "3"]).with_random(SplitMix::new(2));
+        for _ in 0..20 {
+            trainer.record("1", false);
+            trainer.record("2", true);
+            trainer.record("3", true);
+        }
+        trainer.record("4", false);
+        assert_eq!(trainer.record_of("1"), Record { hits: 0, misses: 20 });
+        assert_eq!(trainer.record_of("4"), Record::default());
+        let picks = (0..300).filter(|_| trainer.pick().unwrap().case == "1").count();
+        assert!(picks > 250, "{}", picks);
+    }
+}
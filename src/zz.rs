@@ -0,0 +1,157 @@
+use std::sync::OnceLock;
+use crate::Cube;
+use crate::facelet::*;
+use crate::notation::{Layer::*, Move};
+use crate::search::{Pruning, Search};
+use crate::tables::cached;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Axis {
+    FB,
+    RL,
+    UD
+}
+
+impl Axis {
+    // turns the axis to F/B, where edge orientation is read
+    fn rotation(self) -> Move {
+        match self {
+            Axis::FB => Move::new(Y, 0),
+            Axis::RL => Move::new(Y, 1),
+            Axis::UD => Move::new(X, 1)
+        }
+    }
+}
+
+const LINE: [usize; 2] = [5, 7];            // DF DB
+const CROSS: [usize; 4] = [5, 7, 4, 6];     // DF DB DR DL
+
+// Misoriented edges by slot (see EDGE_NAMES), read from the stickers and
+// the centres, so it works on typed in scans as well. An edge is good if
+// its U/D sticker faces U/D, or, with no U/D colour in the way, if its F/B
+// sticker does.
+pub fn misoriented_edges(cube: &Cube, axis: Axis) -> Vec<usize> {
+    let rotation = axis.rotation();
+    let mut view = cube.clone();
+    view.apply(rotation);
+    let perm = move_perm(rotation);
+    let colors = view.centre_colors();
+    let up_down = [colors[0], colors[5]];
+    let front_back = [colors[2], colors[4]];
+    let mut result = Vec::new();
+    for (slot, edge) in EDGE_FACELETS.iter().enumerate() {
+        let moved = facelet(perm[sticker(edge[0]) as usize]);
+        let view_slot = EDGE_FACELETS.iter().find(|e| e.contains(&moved)).unwrap();
+        let first = view.color(view_slot[0]);
+        let second = view.color(view_slot[1]);
+        let good = up_down.contains(&first)
+            || (front_back.contains(&first) && !up_down.contains(&second));
+        if !good {
+            result.push(slot);
+        }
+    }
+    result
+}
+
+fn eo_tables(pieces: &[usize]) -> [Pruning; 2] {
+    let moves = Move::all(&[U, D, L, R, F, B]);
+    let size = pieces.len();
+    let goal: Vec<u8> = pieces.iter().map(|&e| edge_home(e))
+        .chain((0..12).map(edge_home))
+        .collect();
+    [
+        Pruning::identical((size..size + 12).collect(), &goal, &moves, 20),
+        Pruning::new((0..size).collect(), &goal, &moves, 20)
+    ]
+}
+
+fn eo_search(cube: &Cube, rotation: &[Move], pieces: &[usize], tables: &[Pruning]) -> Option<Vec<Move>> {
+    let mut view = cube.clone();
+    view.apply_alg(rotation);
+    let scheme = view.centre_colors();
+    let mut start = Vec::new();
+    for edge in pieces.iter().copied().chain(0..12) {
+        start.push(view.edge_sticker_in(edge, &scheme)?);
+    }
+    let size = pieces.len();
+    let homes: Vec<u8> = (0..12).map(edge_home).collect();
+    let goal = |s: &[u8]| {
+        s[..size].iter().zip(pieces).all(|(&s, &e)| s == edge_home(e))
+            && s[size..].iter().all(|s| homes.contains(s))
+    };
+    let moves = Move::all(&[U, D, L, R, F, B]);
+    Search { moves: &moves, tables, goal: &goal }.first(&start, 16)
+}
+
+// Optimal EOLine, the moves go after the rotation
+pub fn eoline(cube: &Cube, rotation: &[Move]) -> Option<Vec<Move>> {
+    static TABLES: OnceLock<[Pruning; 2]> = OnceLock::new();
+    eo_search(cube, rotation, &LINE, TABLES.get_or_init(|| cached("eoline", || eo_tables(&LINE))))
+}
+
+// Optimal EOCross, the moves go after the rotation
+pub fn eocross(cube: &Cube, rotation: &[Move]) -> Option<Vec<Move>> {
+    static TABLES: OnceLock<[Pruning; 2]> = OnceLock::new();
+    eo_search(cube, rotation, &CROSS, TABLES.get_or_init(|| cached("eocross", || eo_tables(&CROSS))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notation::parse_alg;
+
+    const SCRAMBLE: &str = "R2 D L2 B2 L2 U B2 D B2 U' L R' D R' B D' F L2 D F";
+
+    fn scrambled(alg: &str) -> Cube {
+        let mut cube = Cube::default();
+        cube.apply_alg(&parse_alg(alg).unwrap());
+        cube
+    }
+
+    #[test]
+    fn check_misoriented_edges() {
+        let cube = scrambled("F");
+        assert_eq!(misoriented_edges(&cube, Axis::FB), vec![1, 5, 8, 9]);
+        assert!(misoriented_edges(&cube, Axis::RL).is_empty());
+        assert!(misoriented_edges(&cube, Axis::UD).is_empty());
+        let cube = scrambled("R'");
+        assert_eq!(misoriented_edges(&cube, Axis::RL), vec![0, 4, 8, 11]);
+        assert!(misoriented_edges(&cube, Axis::FB).is_empty());
+        let cube = scrambled("U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2");
+        for axis in [Axis::FB, Axis::RL, Axis::UD] {
+            assert_eq!(misoriented_edges(&cube, axis).len(), 12);
+        }
+    }
+
+    #[test]
+    fn check_misoriented_edges_on_scan() {
+        let cube = Cube::from_scan(crate::test::SCRAMBLED_SCAN);
+        assert_eq!(misoriented_edges(&cube, Axis::FB), misoriented_edges(&scrambled(SCRAMBLE), Axis::FB));
+        assert_eq!(misoriented_edges(&cube, Axis::FB).len() % 2, 0);
+    }
+
+    #[test]
+    fn check_eoline() {
+        let mut cube = scrambled(SCRAMBLE);
+        let solution = eoline(&cube, &[]).unwrap();
+        cube.apply_alg(&solution);
+        assert!(misoriented_edges(&cube, Axis::FB).is_empty());
+        assert_eq!([cube.edge_sticker(5), cube.edge_sticker(7)], [Some(edge_home(5)), Some(edge_home(7))]);
+        assert_eq!(eoline(&scrambled("F R"), &[]), parse_alg("R' F'"));
+    }
+
+    #[test]
+    fn check_eocross() {
+        let rotation = parse_alg("x2").unwrap();
+        let mut cube = scrambled(SCRAMBLE);
+        let solution = eocross(&cube, &rotation).unwrap();
+        cube.apply_alg(&rotation);
+        cube.apply_alg(&solution);
+        assert!(misoriented_edges(&cube, Axis::FB).is_empty());
+        let scheme = cube.centre_colors();
+        for edge in CROSS {
+            assert_eq!(cube.edge_sticker_in(edge, &scheme), Some(edge_home(edge)));
+        }
+        assert!(solution.len() >= eoline(&scrambled(SCRAMBLE), &rotation).unwrap().len());
+    }
+}